@@ -120,6 +120,10 @@ pub enum VP8StatusCode {
     VP8_STATUS_NOT_ENOUGH_DATA = 7,
 }
 
+/// # Safety
+///
+/// `idec` must be a live incremental decoder and every out-pointer must
+/// be valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPIDecGetYUV(
     idec: *const WebPIDecoder,
@@ -379,11 +383,18 @@ extern "C" {
     ) -> VP8StatusCode;
 }
 
+/// # Safety
+///
+/// `buffer` must be valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPInitDecBuffer(buffer: *mut WebPDecBuffer) -> c_int {
     WebPInitDecBufferInternal(buffer, WEBP_DECODER_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `data` must be readable for `data_size` bytes and `features` valid for
+/// writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPGetFeatures(
     data: *const u8,
@@ -393,6 +404,9 @@ pub unsafe extern "C" fn WebPGetFeatures(
     WebPGetFeaturesInternal(data, data_size, features, WEBP_DECODER_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `config` must be valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPInitDecoderConfig(config: *mut WebPDecoderConfig) -> c_int {
     WebPInitDecoderConfigInternal(config, WEBP_DECODER_ABI_VERSION)
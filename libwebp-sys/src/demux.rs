@@ -19,6 +19,7 @@ cfg_if! {
 pub struct WebPDemuxer(c_void);
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPDemuxState {
     WEBP_DEMUX_PARSE_ERROR = -1,
@@ -152,11 +153,19 @@ extern "C" {
     pub fn WebPAnimDecoderDelete(dec: *mut WebPAnimDecoder);
 }
 
+/// # Safety
+///
+/// `data` must point to a `WebPData` whose bytes outlive the returned
+/// demuxer.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPDemux(data: *const WebPData) -> *mut WebPDemuxer {
     WebPDemuxInternal(data, 0, ptr::null_mut(), WEBP_DEMUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `data` must point to a `WebPData` whose bytes outlive the returned
+/// demuxer, and `state` must be null or valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPDemuxPartial(
     data: *const WebPData,
@@ -165,6 +174,9 @@ pub unsafe extern "C" fn WebPDemuxPartial(
     WebPDemuxInternal(data, 1, state, WEBP_DEMUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `dec_options` must be valid for writes.
 #[cfg(feature = "0.5")]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPAnimDecoderOptionsInit(
@@ -173,6 +185,10 @@ pub unsafe extern "C" fn WebPAnimDecoderOptionsInit(
     WebPAnimDecoderOptionsInitInternal(dec_options, WEBP_DEMUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `webp_data` must point to a `WebPData` whose bytes outlive the returned
+/// decoder; `dec_options` may be null.
 #[cfg(feature = "0.5")]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPAnimDecoderNew(
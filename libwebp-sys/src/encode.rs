@@ -16,6 +16,7 @@ cfg_if! {
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPImageHint {
     WEBP_HINT_DEFAULT = 0,
@@ -32,6 +33,7 @@ pub const WEBP_HINT_LAST: c_int = WebPImageHint::WEBP_HINT_LAST;
 
 #[allow(non_snake_case)]
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct WebPConfig {
     pub lossless: c_int,
     pub quality: c_float,
@@ -127,6 +129,7 @@ pub const WEBP_CSP_UV_MASK: c_int = 3;
 pub const WEBP_CSP_ALPHA_BIT: c_int = 4;
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPEncodingError {
     VP8_ENC_OK = 0,
@@ -346,6 +349,9 @@ extern "C" {
     pub fn WebPEncode(config: *const WebPConfig, picture: *mut WebPPicture) -> c_int;
 }
 
+/// # Safety
+///
+/// `config` must be valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPConfigInit(config: *mut WebPConfig) -> c_int {
     WebPConfigInitInternal(
@@ -356,6 +362,9 @@ pub unsafe extern "C" fn WebPConfigInit(config: *mut WebPConfig) -> c_int {
     )
 }
 
+/// # Safety
+///
+/// `config` must be valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPConfigPreset(
     config: *mut WebPConfig,
@@ -365,6 +374,10 @@ pub unsafe extern "C" fn WebPConfigPreset(
     WebPConfigInitInternal(config, preset, quality, WEBP_ENCODER_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `picture` must be valid for writes. Any buffers it owned are leaked,
+/// not freed.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPPictureInit(picture: *mut WebPPicture) -> c_int {
     WebPPictureInitInternal(picture, WEBP_ENCODER_ABI_VERSION)
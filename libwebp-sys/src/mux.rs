@@ -20,6 +20,7 @@ cfg_if! {
 pub struct WebPMux(c_void);
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPMuxError {
     WEBP_MUX_OK = 1,
@@ -31,6 +32,7 @@ pub enum WebPMuxError {
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPChunkId {
     WEBP_CHUNK_VP8X,
@@ -167,11 +169,18 @@ extern "C" {
     pub fn WebPAnimEncoderDelete(enc: *mut WebPAnimEncoder);
 }
 
+/// # Safety
+///
+/// The returned mux must be released with `WebPMuxDelete`.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPMuxNew() -> *mut WebPMux {
     WebPNewInternal(WEBP_MUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `bitstream` must point to a valid `WebPData`. With `copy_data == 0`
+/// its bytes must outlive the returned mux.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPMuxCreate(
     bitstream: *const WebPData,
@@ -180,6 +189,9 @@ pub unsafe extern "C" fn WebPMuxCreate(
     WebPMuxCreateInternal(bitstream, copy_data, WEBP_MUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `enc_options` must be valid for writes.
 #[cfg(feature = "0.5")]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPAnimEncoderOptionsInit(
@@ -188,6 +200,9 @@ pub unsafe extern "C" fn WebPAnimEncoderOptionsInit(
     WebPAnimEncoderOptionsInitInternal(enc_options, WEBP_MUX_ABI_VERSION)
 }
 
+/// # Safety
+///
+/// `enc_options` must be null or point to initialized options.
 #[cfg(feature = "0.5")]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPAnimEncoderNew(
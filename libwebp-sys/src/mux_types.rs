@@ -11,18 +11,19 @@ pub use self::WebPMuxAnimDispose::*;
 #[allow(non_camel_case_types)]
 #[repr(C)]
 pub enum WebPFeatureFlags {
-    #[cfg(not(feature = "0.6.0"))]
+    #[cfg(not(feature = "0.6"))]
     FRAGMENTS_FLAG = 0x00000001,
     ANIMATION_FLAG = 0x00000002,
     XMP_FLAG = 0x00000004,
     EXIF_FLAG = 0x00000008,
     ALPHA_FLAG = 0x00000010,
     ICCP_FLAG = 0x00000020,
-    #[cfg(feature = "0.6.0")]
+    #[cfg(feature = "0.6")]
     ALL_VALID_FLAGS = 0x0000003E,
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPMuxAnimDispose {
     WEBP_MUX_DISPOSE_NONE = 0,
@@ -30,6 +31,7 @@ pub enum WebPMuxAnimDispose {
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
 pub enum WebPMuxAnimBlend {
     WEBP_MUX_BLEND = 0,
@@ -42,6 +44,9 @@ pub struct WebPData {
     pub size: usize,
 }
 
+/// # Safety
+///
+/// `webp_data` must be null or valid for writes.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPDataInit(webp_data: *mut WebPData) {
     if !webp_data.is_null() {
@@ -49,8 +54,13 @@ pub unsafe extern "C" fn WebPDataInit(webp_data: *mut WebPData) {
     }
 }
 
-// Clears the contents of the 'webp_data' object by calling free(). Does not
-// deallocate the object itself.
+/// Clears the contents of the 'webp_data' object by calling free(). Does not
+/// deallocate the object itself.
+///
+/// # Safety
+///
+/// `webp_data` must be null or point to a `WebPData` whose bytes were
+/// allocated with `malloc`.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPDataClear(webp_data: *mut WebPData) {
     if !webp_data.is_null() {
@@ -59,8 +69,13 @@ pub unsafe extern "C" fn WebPDataClear(webp_data: *mut WebPData) {
     }
 }
 
-// Allocates necessary storage for 'dst' and copies the contents of 'src'.
-// Returns true on success.
+/// Allocates necessary storage for 'dst' and copies the contents of 'src'.
+/// Returns true on success.
+///
+/// # Safety
+///
+/// `src` and `dst` must be null or valid, and `src` must describe readable
+/// memory. The previous contents of `dst` are not freed.
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn WebPDataCopy(src: *const WebPData, dst: *mut WebPData) -> c_int {
     if src.is_null() || dst.is_null() {
@@ -79,5 +94,5 @@ pub unsafe extern "C" fn WebPDataCopy(src: *const WebPData, dst: *mut WebPData)
         );
         (*dst).size = (*src).size;
     }
-    return 1;
+    1
 }
@@ -0,0 +1,79 @@
+//! Compares the reusable `Encoder` with the one-shot `WebPEncodeRGBA`.
+//!
+//! Run with `cargo run --release -p libwebp --example encoder_bench`.
+
+extern crate libwebp;
+
+use std::time::{Duration, Instant};
+
+use libwebp::{Encoder, WebPConfigInit, WebPEncodeLosslessRGBA, WebPEncodeRGBA};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+const ITERATIONS: u32 = 500;
+
+fn thumbnail(seed: u32) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let v = x.wrapping_mul(31) ^ y.wrapping_mul(17) ^ seed;
+            rgba.extend_from_slice(&[v as u8, (v >> 2) as u8, (x + y) as u8, 0xff]);
+        }
+    }
+    rgba
+}
+
+fn measure<F: FnMut(&[u8]) -> usize>(name: &str, images: &[Vec<u8>], mut encode: F) {
+    let mut total = 0;
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        total += encode(&images[i as usize % images.len()]);
+    }
+    let elapsed = start.elapsed();
+    let per_image = elapsed / ITERATIONS;
+    println!(
+        "{:<20} {:>10.3} ms/image ({} bytes total)",
+        name,
+        as_millis(per_image),
+        total
+    );
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e3 + f64::from(duration.subsec_nanos()) / 1e6
+}
+
+fn main() {
+    let images = (0..8).map(thumbnail).collect::<Vec<_>>();
+    let stride = WIDTH * 4;
+
+    measure("one-shot lossy", &images, |rgba| {
+        WebPEncodeRGBA(rgba, WIDTH, HEIGHT, stride, 75.0)
+            .unwrap()
+            .len()
+    });
+    let mut encoder = Encoder::new(WebPConfigInit().unwrap()).unwrap();
+    measure("encoder lossy", &images, |rgba| {
+        encoder
+            .encode_rgba(rgba, WIDTH, HEIGHT, stride)
+            .unwrap()
+            .len()
+    });
+
+    measure("one-shot lossless", &images, |rgba| {
+        WebPEncodeLosslessRGBA(rgba, WIDTH, HEIGHT, stride)
+            .unwrap()
+            .len()
+    });
+    let mut config = WebPConfigInit().unwrap();
+    config.set_lossless(true);
+    // same settings as WebPEncodeLosslessRGBA
+    config.set_quality(70.0);
+    let mut encoder = Encoder::new(config).unwrap();
+    measure("encoder lossless", &images, |rgba| {
+        encoder
+            .encode_rgba(rgba, WIDTH, HEIGHT, stride)
+            .unwrap()
+            .len()
+    });
+}
@@ -14,6 +14,9 @@ pub struct WebpBox<T: ?Sized> {
 }
 
 impl<T: ?Sized> WebpBox<T> {
+    /// # Safety
+    ///
+    /// `raw` must be a non-null pointer allocated by libwebp (or `malloc`).
     pub unsafe fn from_raw(raw: *mut T) -> Self {
         Self {
             ptr: NonNull::new_unchecked(raw),
@@ -34,7 +37,7 @@ impl<T: ?Sized> WebpBox<T> {
 
 impl<T> WebpBox<[T]> {
     pub(crate) unsafe fn from_raw_parts(data: *mut T, len: usize) -> Self {
-        let raw = slice::from_raw_parts_mut(data as *mut T, len) as *mut [T];
+        let raw = slice::from_raw_parts_mut(data, len) as *mut [T];
         Self::from_raw(raw)
     }
 }
@@ -108,9 +111,6 @@ impl<T: PartialEq<T> + ?Sized> PartialEq<WebpBox<T>> for WebpBox<T> {
     fn eq(&self, other: &WebpBox<T>) -> bool {
         T::eq(self, other)
     }
-    fn ne(&self, other: &WebpBox<T>) -> bool {
-        T::ne(self, other)
-    }
 }
 
 impl<T: Eq + ?Sized> Eq for WebpBox<T> {}
@@ -1,6 +1,6 @@
 use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::os::raw::*;
 use std::ptr::{self, NonNull};
 use std::slice;
@@ -8,7 +8,7 @@ use std::slice;
 use libwebp_sys as sys;
 
 use boxed::{WebpBox, WebpYuvBox};
-use error::WebpUnknownError;
+use error::WebpError;
 use ffi_utils::check_int;
 
 pub use libwebp_sys::{VP8StatusCode, WEBP_CSP_MODE};
@@ -25,117 +25,63 @@ pub fn WebPGetDecoderVersion() -> u32 {
 }
 
 #[allow(non_snake_case)]
-pub fn WebPGetInfo(data: &[u8]) -> Result<(u32, u32), WebpUnknownError> {
+pub fn WebPGetInfo(data: &[u8]) -> Result<(u32, u32), WebpError> {
     let mut width: c_int = 0;
     let mut height: c_int = 0;
     let res = unsafe { sys::WebPGetInfo(data.as_ptr(), data.len(), &mut width, &mut height) };
     if res != 0 {
         Ok((width as u32, height as u32))
     } else {
-        Err(WebpUnknownError)
+        Err(header_error(data))
     }
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeRGBA(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let res = unsafe { sys::WebPDecodeRGBA(data.as_ptr(), data.len(), &mut width, &mut height) };
-    if !res.is_null() {
-        let b = unsafe { WebpBox::from_raw_parts(res, width as usize * height as usize * 4) };
-        Ok((width as u32, height as u32, b))
-    } else {
-        Err(WebpUnknownError)
-    }
+pub fn WebPDecodeRGBA(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    decode_rgb(data, WEBP_CSP_MODE::MODE_RGBA)
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeARGB(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let res = unsafe { sys::WebPDecodeARGB(data.as_ptr(), data.len(), &mut width, &mut height) };
-    if !res.is_null() {
-        let b = unsafe { WebpBox::from_raw_parts(res, width as usize * height as usize * 4) };
-        Ok((width as u32, height as u32, b))
-    } else {
-        Err(WebpUnknownError)
-    }
+pub fn WebPDecodeARGB(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    decode_rgb(data, WEBP_CSP_MODE::MODE_ARGB)
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeBGRA(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let res = unsafe { sys::WebPDecodeBGRA(data.as_ptr(), data.len(), &mut width, &mut height) };
-    if !res.is_null() {
-        let b = unsafe { WebpBox::from_raw_parts(res, width as usize * height as usize * 4) };
-        Ok((width as u32, height as u32, b))
-    } else {
-        Err(WebpUnknownError)
-    }
+pub fn WebPDecodeBGRA(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    decode_rgb(data, WEBP_CSP_MODE::MODE_BGRA)
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeRGB(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let res = unsafe { sys::WebPDecodeRGB(data.as_ptr(), data.len(), &mut width, &mut height) };
-    if !res.is_null() {
-        let b = unsafe { WebpBox::from_raw_parts(res, width as usize * height as usize * 3) };
-        Ok((width as u32, height as u32, b))
-    } else {
-        Err(WebpUnknownError)
-    }
+pub fn WebPDecodeRGB(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    decode_rgb(data, WEBP_CSP_MODE::MODE_RGB)
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeBGR(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let res = unsafe { sys::WebPDecodeBGR(data.as_ptr(), data.len(), &mut width, &mut height) };
-    if !res.is_null() {
-        let b = unsafe { WebpBox::from_raw_parts(res, width as usize * height as usize * 3) };
-        Ok((width as u32, height as u32, b))
-    } else {
-        Err(WebpUnknownError)
-    }
+pub fn WebPDecodeBGR(data: &[u8]) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    decode_rgb(data, WEBP_CSP_MODE::MODE_BGR)
 }
 
 #[allow(non_snake_case)]
-pub fn WebPDecodeYUV(data: &[u8]) -> Result<(u32, u32, u32, u32, WebpYuvBox), WebpUnknownError> {
-    let mut width: c_int = 0;
-    let mut height: c_int = 0;
-    let mut u: *mut u8 = ptr::null_mut();
-    let mut v: *mut u8 = ptr::null_mut();
-    let mut stride: c_int = 0;
-    let mut uv_stride: c_int = 0;
-    let res = unsafe {
-        sys::WebPDecodeYUV(
-            data.as_ptr(),
-            data.len(),
-            &mut width,
-            &mut height,
-            &mut u,
-            &mut v,
-            &mut stride,
-            &mut uv_stride,
+pub fn WebPDecodeYUV(data: &[u8]) -> Result<(u32, u32, u32, u32, WebpYuvBox), WebpError> {
+    let output = decode(data, |output| output.colorspace = WEBP_CSP_MODE::MODE_YUV)?;
+    let yuva = unsafe { output.u.YUVA };
+    let yuv = unsafe {
+        WebpYuvBox::from_raw_parts(
+            yuva.y,
+            yuva.y_size,
+            yuva.u,
+            yuva.u_size,
+            yuva.v,
+            yuva.v_size,
         )
     };
-    if !res.is_null() {
-        let uv_height = (height + 1) / 2;
-        let y_size = stride as usize * height as usize;
-        let uv_size = uv_stride as usize * uv_height as usize;
-        let yuv = unsafe { WebpYuvBox::from_raw_parts(res, y_size, u, uv_size, v, uv_size) };
-        Ok((
-            width as u32,
-            height as u32,
-            stride as u32,
-            uv_stride as u32,
-            yuv,
-        ))
-    } else {
-        Err(WebpUnknownError)
-    }
+    Ok((
+        output.width as u32,
+        output.height as u32,
+        yuva.y_stride as u32,
+        yuva.u_stride as u32,
+        yuv,
+    ))
 }
 
 #[allow(non_snake_case)]
@@ -143,21 +89,9 @@ pub fn WebPDecodeRGBAInto(
     data: &[u8],
     output_buffer: &mut [u8],
     output_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeRGBAInto(
-            data.as_ptr(),
-            data.len(),
-            output_buffer.as_mut_ptr(),
-            output_buffer.len(),
-            check_int!(output_stride),
-        )
-    };
-    if !res.is_null() {
-        Ok(())
-    } else {
-        Err(WebpUnknownError)
-    }
+) -> Result<(), WebpError> {
+    let output_stride = check_int!(output_stride)?;
+    decode_rgb_into(data, WEBP_CSP_MODE::MODE_RGBA, output_buffer, output_stride)
 }
 
 #[allow(non_snake_case)]
@@ -165,21 +99,9 @@ pub fn WebPDecodeARGBInto(
     data: &[u8],
     output_buffer: &mut [u8],
     output_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeARGBInto(
-            data.as_ptr(),
-            data.len(),
-            output_buffer.as_mut_ptr(),
-            output_buffer.len(),
-            check_int!(output_stride),
-        )
-    };
-    if !res.is_null() {
-        Ok(())
-    } else {
-        Err(WebpUnknownError)
-    }
+) -> Result<(), WebpError> {
+    let output_stride = check_int!(output_stride)?;
+    decode_rgb_into(data, WEBP_CSP_MODE::MODE_ARGB, output_buffer, output_stride)
 }
 
 #[allow(non_snake_case)]
@@ -187,21 +109,9 @@ pub fn WebPDecodeBGRAInto(
     data: &[u8],
     output_buffer: &mut [u8],
     output_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeBGRAInto(
-            data.as_ptr(),
-            data.len(),
-            output_buffer.as_mut_ptr(),
-            output_buffer.len(),
-            check_int!(output_stride),
-        )
-    };
-    if !res.is_null() {
-        Ok(())
-    } else {
-        Err(WebpUnknownError)
-    }
+) -> Result<(), WebpError> {
+    let output_stride = check_int!(output_stride)?;
+    decode_rgb_into(data, WEBP_CSP_MODE::MODE_BGRA, output_buffer, output_stride)
 }
 
 #[allow(non_snake_case)]
@@ -209,21 +119,9 @@ pub fn WebPDecodeRGBInto(
     data: &[u8],
     output_buffer: &mut [u8],
     output_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeRGBInto(
-            data.as_ptr(),
-            data.len(),
-            output_buffer.as_mut_ptr(),
-            output_buffer.len(),
-            check_int!(output_stride),
-        )
-    };
-    if !res.is_null() {
-        Ok(())
-    } else {
-        Err(WebpUnknownError)
-    }
+) -> Result<(), WebpError> {
+    let output_stride = check_int!(output_stride)?;
+    decode_rgb_into(data, WEBP_CSP_MODE::MODE_RGB, output_buffer, output_stride)
 }
 
 #[allow(non_snake_case)]
@@ -231,21 +129,9 @@ pub fn WebPDecodeBGRInto(
     data: &[u8],
     output_buffer: &mut [u8],
     output_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeBGRInto(
-            data.as_ptr(),
-            data.len(),
-            output_buffer.as_mut_ptr(),
-            output_buffer.len(),
-            check_int!(output_stride),
-        )
-    };
-    if !res.is_null() {
-        Ok(())
-    } else {
-        Err(WebpUnknownError)
-    }
+) -> Result<(), WebpError> {
+    let output_stride = check_int!(output_stride)?;
+    decode_rgb_into(data, WEBP_CSP_MODE::MODE_BGR, output_buffer, output_stride)
 }
 
 #[allow(non_snake_case)]
@@ -257,29 +143,85 @@ pub fn WebPDecodeYUVInto(
     u_stride: u32,
     v: &mut [u8],
     v_stride: u32,
-) -> Result<(), WebpUnknownError> {
-    let res = unsafe {
-        sys::WebPDecodeYUVInto(
-            data.as_ptr(),
-            data.len(),
-            luma.as_mut_ptr(),
-            luma.len(),
-            check_int!(luma_stride),
-            u.as_mut_ptr(),
-            u.len(),
-            check_int!(u_stride),
-            v.as_mut_ptr(),
-            v.len(),
-            check_int!(v_stride),
-        )
+) -> Result<(), WebpError> {
+    let yuva = sys::WebPYUVABuffer {
+        y: luma.as_mut_ptr(),
+        u: u.as_mut_ptr(),
+        v: v.as_mut_ptr(),
+        a: ptr::null_mut(),
+        y_stride: check_int!(luma_stride)?,
+        u_stride: check_int!(u_stride)?,
+        v_stride: check_int!(v_stride)?,
+        a_stride: 0,
+        y_size: luma.len(),
+        u_size: u.len(),
+        v_size: v.len(),
+        a_size: 0,
     };
-    if !res.is_null() {
-        Ok(())
+    decode(data, |output| {
+        output.colorspace = WEBP_CSP_MODE::MODE_YUV;
+        output.is_external_memory = 1;
+        output.u.YUVA = yuva;
+    })
+    .map(|_| ())
+}
+
+// The simple decoding functions only return NULL on failure, so they are
+// built on `WebPDecode`, which reports the status as well.
+fn decode<F>(data: &[u8], set_output: F) -> Result<sys::WebPDecBuffer, WebpError>
+where
+    F: FnOnce(&mut sys::WebPDecBuffer),
+{
+    let mut config = MaybeUninit::<sys::WebPDecoderConfig>::uninit();
+    if unsafe { sys::WebPInitDecoderConfig(config.as_mut_ptr()) } == 0 {
+        return Err(WebpError::Decode(VP8StatusCode::VP8_STATUS_INVALID_PARAM));
+    }
+    let mut config = unsafe { config.assume_init() };
+    set_output(&mut config.output);
+    let status = unsafe { sys::WebPDecode(data.as_ptr(), data.len(), &mut config) };
+    if status == VP8StatusCode::VP8_STATUS_OK {
+        // the caller takes over the pixels the decoder allocated, if any
+        Ok(config.output)
     } else {
-        Err(WebpUnknownError)
+        unsafe {
+            sys::WebPFreeDecBuffer(&mut config.output);
+        }
+        // `WebPDecode` reports truncated headers as bitstream errors
+        if status == VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR {
+            WebPGetFeatures(data)?;
+        }
+        Err(WebpError::Decode(status))
     }
 }
 
+fn decode_rgb(
+    data: &[u8],
+    colorspace: WEBP_CSP_MODE,
+) -> Result<(u32, u32, WebpBox<[u8]>), WebpError> {
+    let output = decode(data, |output| output.colorspace = colorspace)?;
+    let rgba = unsafe { output.u.RGBA };
+    let b = unsafe { WebpBox::from_raw_parts(rgba.rgba, rgba.size) };
+    Ok((output.width as u32, output.height as u32, b))
+}
+
+fn decode_rgb_into(
+    data: &[u8],
+    colorspace: WEBP_CSP_MODE,
+    output_buffer: &mut [u8],
+    output_stride: c_int,
+) -> Result<(), WebpError> {
+    decode(data, |output| {
+        output.colorspace = colorspace;
+        output.is_external_memory = 1;
+        output.u.RGBA = sys::WebPRGBABuffer {
+            rgba: output_buffer.as_mut_ptr(),
+            stride: output_stride,
+            size: output_buffer.len(),
+        };
+    })
+    .map(|_| ())
+}
+
 #[allow(non_snake_case)]
 pub fn WebPIsPremultipliedMode(mode: WEBP_CSP_MODE) -> bool {
     use self::WEBP_CSP_MODE::*;
@@ -303,7 +245,7 @@ pub fn WebPIsRGBMode(mode: WEBP_CSP_MODE) -> bool {
     mode < MODE_YUV
 }
 
-// #[repr(transparent)] // TODO: MSRV >= 1.28.0
+#[repr(transparent)]
 pub struct WebPDecBuffer<'a>(sys::WebPDecBuffer, PhantomData<&'a mut ()>);
 
 impl<'a> Drop for WebPDecBuffer<'a> {
@@ -350,12 +292,9 @@ impl<'a> WebPDecBuffer<'a> {
         }
     }
 
-    pub fn set_rgba_buffer(&mut self, rgba: &'a mut [u8], stride: u32) {
-        assert!(
-            self.0.private_memory.is_null(),
-            "Internal buffer cannot be turned into external"
-        );
-        let stride = check_int!(stride);
+    pub fn set_rgba_buffer(&mut self, rgba: &'a mut [u8], stride: u32) -> Result<(), WebpError> {
+        self.check_no_private_memory()?;
+        let stride = check_int!(stride)?;
         if !WebPIsRGBMode(self.0.colorspace) {
             self.0.colorspace = WEBP_CSP_MODE::MODE_RGB;
         }
@@ -368,14 +307,21 @@ impl<'a> WebPDecBuffer<'a> {
             rgbabuf.size = rgba.len();
             rgbabuf.stride = stride;
         }
+        Ok(())
     }
 
-    pub fn with_rgba_buffer<'b>(self, rgba: &'b mut [u8], stride: u32) -> WebPDecBuffer<'b> {
+    pub fn with_rgba_buffer<'b>(
+        self,
+        rgba: &'b mut [u8],
+        stride: u32,
+    ) -> Result<WebPDecBuffer<'b>, WebpError> {
         let mut this = unsafe { mem::transmute::<WebPDecBuffer<'a>, WebPDecBuffer<'b>>(self) };
-        this.set_rgba_buffer(rgba, stride);
-        this
+        this.set_rgba_buffer(rgba, stride)?;
+        Ok(this)
     }
 
+    // a buffer and a stride for each plane
+    #[allow(clippy::too_many_arguments)]
     pub fn set_yuva_buffer(
         &mut self,
         y: &'a mut [u8],
@@ -386,15 +332,12 @@ impl<'a> WebPDecBuffer<'a> {
         u_stride: u32,
         v_stride: u32,
         a_stride: u32,
-    ) {
-        assert!(
-            self.0.private_memory.is_null(),
-            "Internal buffer cannot be turned into external"
-        );
-        let y_stride = check_int!(y_stride);
-        let u_stride = check_int!(u_stride);
-        let v_stride = check_int!(v_stride);
-        let a_stride = check_int!(a_stride);
+    ) -> Result<(), WebpError> {
+        self.check_no_private_memory()?;
+        let y_stride = check_int!(y_stride)?;
+        let u_stride = check_int!(u_stride)?;
+        let v_stride = check_int!(v_stride)?;
+        let a_stride = check_int!(a_stride)?;
         if WebPIsRGBMode(self.0.colorspace) {
             self.0.colorspace = WEBP_CSP_MODE::MODE_YUV;
         }
@@ -416,8 +359,10 @@ impl<'a> WebPDecBuffer<'a> {
             yuvabuf.v_stride = v_stride;
             yuvabuf.a_stride = a_stride;
         }
+        Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn with_yuva_buffer<'b>(
         self,
         y: &'b mut [u8],
@@ -428,10 +373,20 @@ impl<'a> WebPDecBuffer<'a> {
         u_stride: u32,
         v_stride: u32,
         a_stride: u32,
-    ) -> WebPDecBuffer<'b> {
+    ) -> Result<WebPDecBuffer<'b>, WebpError> {
         let mut this = unsafe { mem::transmute::<WebPDecBuffer<'a>, WebPDecBuffer<'b>>(self) };
-        this.set_yuva_buffer(y, u, v, a, y_stride, u_stride, v_stride, a_stride);
-        this
+        this.set_yuva_buffer(y, u, v, a, y_stride, u_stride, v_stride, a_stride)?;
+        Ok(this)
+    }
+
+    fn check_no_private_memory(&self) -> Result<(), WebpError> {
+        if self.0.private_memory.is_null() {
+            Ok(())
+        } else {
+            Err(WebpError::InvalidArgument(
+                "a buffer holding decoded pixels cannot be made external".to_owned(),
+            ))
+        }
     }
 
     pub fn set_to_internal(&mut self) {
@@ -458,15 +413,13 @@ impl<'a> fmt::Debug for WebPDecBuffer<'a> {
 }
 
 #[allow(non_snake_case)]
-pub fn WebPInitDecBuffer() -> Result<WebPDecBuffer<'static>, WebpUnknownError> {
-    // TODO: use MaybeUninit (MSRV >= nightly)
-    let mut buffer = unsafe { mem::uninitialized() };
-    let res = unsafe { sys::WebPInitDecBuffer(&mut buffer) };
+pub fn WebPInitDecBuffer() -> Result<WebPDecBuffer<'static>, WebpError> {
+    let mut buffer = MaybeUninit::uninit();
+    let res = unsafe { sys::WebPInitDecBuffer(buffer.as_mut_ptr()) };
     if res != 0 {
-        Ok(WebPDecBuffer(buffer, PhantomData))
+        Ok(WebPDecBuffer(unsafe { buffer.assume_init() }, PhantomData))
     } else {
-        mem::forget(buffer);
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -498,7 +451,7 @@ impl<'a> fmt::Debug for WebPIDecoder<'a> {
 #[allow(non_snake_case)]
 pub fn WebPINewDecoder<'a, 'b>(
     output_buffer: Option<&'b mut WebPDecBuffer<'a>>,
-) -> Result<WebPIDecoder<'b>, WebpUnknownError> {
+) -> Result<WebPIDecoder<'b>, WebpError> {
     let output_buffer = match output_buffer {
         None => ptr::null_mut(),
         Some(p) => (&mut p.0) as *mut sys::WebPDecBuffer,
@@ -507,7 +460,7 @@ pub fn WebPINewDecoder<'a, 'b>(
     if let Some(ptr) = NonNull::new(res) {
         Ok(WebPIDecoder(ptr, PhantomData))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -524,24 +477,25 @@ pub fn WebPINewRGB<'a>(
     csp: WEBP_CSP_MODE,
     output_buffer: Option<&'a mut [u8]>,
     output_stride: u32,
-) -> Result<WebPIDecoder<'a>, WebpUnknownError> {
+) -> Result<WebPIDecoder<'a>, WebpError> {
     let (output_buffer, output_buffer_size) = opt_slice_mut(output_buffer);
     let res = unsafe {
         sys::WebPINewRGB(
             csp,
             output_buffer,
             output_buffer_size,
-            check_int!(output_stride),
+            check_int!(output_stride)?,
         )
     };
     if let Some(ptr) = NonNull::new(res) {
         Ok(WebPIDecoder(ptr, PhantomData))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
-#[allow(non_snake_case)]
+// mirrors the C signature
+#[allow(non_snake_case, clippy::too_many_arguments)]
 pub fn WebPINewYUVA<'a>(
     luma: Option<&'a mut [u8]>,
     luma_stride: u32,
@@ -551,7 +505,7 @@ pub fn WebPINewYUVA<'a>(
     v_stride: u32,
     a: Option<&'a mut [u8]>,
     a_stride: u32,
-) -> Result<WebPIDecoder<'a>, WebpUnknownError> {
+) -> Result<WebPIDecoder<'a>, WebpError> {
     let (luma, luma_size) = opt_slice_mut(luma);
     let (u, u_size) = opt_slice_mut(u);
     let (v, v_size) = opt_slice_mut(v);
@@ -560,22 +514,22 @@ pub fn WebPINewYUVA<'a>(
         sys::WebPINewYUVA(
             luma,
             luma_size,
-            check_int!(luma_stride),
+            check_int!(luma_stride)?,
             u,
             u_size,
-            check_int!(u_stride),
+            check_int!(u_stride)?,
             v,
             v_size,
-            check_int!(v_stride),
+            check_int!(v_stride)?,
             a,
             a_size,
-            check_int!(a_stride),
+            check_int!(a_stride)?,
         )
     };
     if let Some(ptr) = NonNull::new(res) {
         Ok(WebPIDecoder(ptr, PhantomData))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -588,7 +542,7 @@ pub fn WebPINewYUV<'a>(
     u_stride: u32,
     v: Option<&'a mut [u8]>,
     v_stride: u32,
-) -> Result<WebPIDecoder<'a>, WebpUnknownError> {
+) -> Result<WebPIDecoder<'a>, WebpError> {
     let (luma, luma_size) = opt_slice_mut(luma);
     let (u, u_size) = opt_slice_mut(u);
     let (v, v_size) = opt_slice_mut(v);
@@ -596,19 +550,19 @@ pub fn WebPINewYUV<'a>(
         sys::WebPINewYUV(
             luma,
             luma_size,
-            check_int!(luma_stride),
+            check_int!(luma_stride)?,
             u,
             u_size,
-            check_int!(u_stride),
+            check_int!(u_stride)?,
             v,
             v_size,
-            check_int!(v_stride),
+            check_int!(v_stride)?,
         )
     };
     if let Some(ptr) = NonNull::new(res) {
         Ok(WebPIDecoder(ptr, PhantomData))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -630,7 +584,7 @@ pub fn WebPIUpdate<'a>(idec: &mut WebPIDecoder<'a>, data: &[u8]) -> VP8StatusCod
 #[allow(non_snake_case)]
 pub fn WebPIDecGetRGB<'a, 'b>(
     idec: &'b WebPIDecoder<'a>,
-) -> Result<(&'b [u8], u32, u32, u32, u32), WebpUnknownError> {
+) -> Result<(&'b [u8], u32, u32, u32, u32), WebpError> {
     let mut last_y: c_int = 0;
     let mut width: c_int = 0;
     let mut height: c_int = 0;
@@ -652,11 +606,12 @@ pub fn WebPIDecGetRGB<'a, 'b>(
         let data = unsafe { slice::from_raw_parts(res, stride as usize * last_y as usize) };
         Ok((data, last_y, width, height, stride))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
-#[allow(non_snake_case)]
+// the C out-parameters, returned as a tuple
+#[allow(non_snake_case, clippy::type_complexity)]
 pub fn WebPIDecGetYUVA<'a, 'b>(
     idec: &'b WebPIDecoder<'a>,
 ) -> Result<
@@ -672,7 +627,7 @@ pub fn WebPIDecGetYUVA<'a, 'b>(
         u32,
         u32,
     ),
-    WebpUnknownError,
+    WebpError,
 > {
     let mut last_y: c_int = 0;
     let mut u: *mut u8 = ptr::null_mut();
@@ -716,15 +671,15 @@ pub fn WebPIDecGetYUVA<'a, 'b>(
             y, last_y, u, v, a, width, height, stride, uv_stride, a_stride,
         ))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
 #[deprecated(note = "Use WebPIDecGetYUVA.")]
-#[allow(non_snake_case)]
+#[allow(non_snake_case, clippy::type_complexity)]
 pub fn WebPIDecGetYUV<'a, 'b>(
     idec: &'b WebPIDecoder<'a>,
-) -> Result<(&'b [u8], u32, &'b [u8], &'b [u8], u32, u32, u32, u32), WebpUnknownError> {
+) -> Result<(&'b [u8], u32, &'b [u8], &'b [u8], u32, u32, u32, u32), WebpError> {
     WebPIDecGetYUVA(idec).map(|result| {
         let (y, last_y, u, v, _, width, height, stride, uv_stride, _) = result;
         (y, last_y, u, v, width, height, stride, uv_stride)
@@ -734,7 +689,7 @@ pub fn WebPIDecGetYUV<'a, 'b>(
 #[allow(non_snake_case)]
 pub fn WebPIDecodedArea<'a, 'b>(
     idec: &'b WebPIDecoder<'a>,
-) -> Result<(&'b WebPDecBuffer<'b>, u32, u32, u32, u32), WebpUnknownError> {
+) -> Result<(&'b WebPDecBuffer<'b>, u32, u32, u32, u32), WebpError> {
     let mut left: c_int = 0;
     let mut top: c_int = 0;
     let mut width: c_int = 0;
@@ -751,11 +706,11 @@ pub fn WebPIDecodedArea<'a, 'b>(
         let buf = unsafe { &*(buf as *const WebPDecBuffer) };
         Ok((buf, left, top, width, height))
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
-// #[repr(transparent)] // TODO: MSRV >= 1.28.0
+#[repr(transparent)]
 pub struct WebPBitstreamFeatures(sys::WebPBitstreamFeatures);
 
 impl WebPBitstreamFeatures {
@@ -785,7 +740,7 @@ impl WebPBitstreamFeatures {
     }
 }
 
-impl<'a> fmt::Debug for WebPBitstreamFeatures {
+impl fmt::Debug for WebPBitstreamFeatures {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("WebPBitstreamFeatures")
             .field("width", &self.width())
@@ -805,13 +760,22 @@ pub enum WebPBitstreamFormat {
 }
 
 #[allow(non_snake_case)]
-pub fn WebPGetFeatures(data: &[u8]) -> Result<WebPBitstreamFeatures, VP8StatusCode> {
-    let mut features: WebPBitstreamFeatures = unsafe { mem::uninitialized() };
-    let res = unsafe { sys::WebPGetFeatures(data.as_ptr(), data.len(), &mut features.0) };
+pub fn WebPGetFeatures(data: &[u8]) -> Result<WebPBitstreamFeatures, WebpError> {
+    let mut features = MaybeUninit::uninit();
+    let res = unsafe { sys::WebPGetFeatures(data.as_ptr(), data.len(), features.as_mut_ptr()) };
     if res == VP8StatusCode::VP8_STATUS_OK {
-        Ok(features)
+        Ok(WebPBitstreamFeatures(unsafe { features.assume_init() }))
     } else {
-        Err(res)
+        Err(WebpError::Decode(res))
+    }
+}
+
+// `WebPGetInfo` only reports failure; `WebPGetFeatures` checks the same
+// headers and tells why.
+fn header_error(data: &[u8]) -> WebpError {
+    match WebPGetFeatures(data) {
+        Ok(_) => WebpError::Unknown,
+        Err(e) => e,
     }
 }
 
@@ -997,6 +961,9 @@ mod tests {
             let image = Image::new(ColorType::RGB, width, height, stride, data.to_vec());
             assert_abs_diff_eq!(image, test_case.image_opaque, epsilon = 1);
         }
+        // the buffer holds the pixels of the last image
+        let mut rgba = vec![0; 4];
+        assert!(buf.with_rgba_buffer(&mut rgba, 4).is_err());
     }
 
     #[test]
@@ -1018,6 +985,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decode_into_invalid_stride() {
+        let data = &test_cases()[0].webp_data;
+        let mut buf = vec![0; 4];
+        match WebPDecodeRGBAInto(data, &mut buf, 0x8000_0000) {
+            Err(WebpError::InvalidArgument(_)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let data = &test_cases()[0].webp_data;
+        assert_eq!(
+            WebPDecodeRGBA(&data[..16]).err(),
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_NOT_ENOUGH_DATA))
+        );
+        // the header is intact, the image data is not
+        assert_eq!(
+            WebPDecodeRGBA(&data[..data.len() - 16]).err(),
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_NOT_ENOUGH_DATA))
+        );
+        // a lossless bitstream with a corrupt body
+        let mut corrupt = b"RIFF\x22\0\0\0WEBPVP8L\x15\0\0\0\x2f\x01\x40\0\0".to_vec();
+        corrupt.extend_from_slice(&[0xff; 16]);
+        corrupt.push(0);
+        assert_eq!(WebPGetInfo(&corrupt).unwrap(), (2, 2));
+        assert_eq!(
+            WebPDecodeRGB(&corrupt).err(),
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR))
+        );
+        let (width, height) = WebPGetInfo(data).unwrap();
+        let mut small = vec![0; (width * height * 4 - 1) as usize];
+        assert_eq!(
+            WebPDecodeRGBAInto(data, &mut small, width * 4).err(),
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_INVALID_PARAM))
+        );
+        assert_eq!(
+            WebPGetInfo(b"RIFF\x00\x00\x00\x00WEBPVP8\x20").err(),
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR))
+        );
+    }
+
     #[test]
     fn test_get_features() {
         let data = b"\
@@ -1030,13 +1040,13 @@ mod tests {
         let feature = WebPGetFeatures(&data[..32]).unwrap();
         assert_eq!(feature.width(), 3);
         assert_eq!(feature.height(), 2);
-        assert_eq!(feature.has_alpha(), false);
-        assert_eq!(feature.has_animation(), false);
+        assert!(!feature.has_alpha());
+        assert!(!feature.has_animation());
         assert_eq!(feature.format(), WebPBitstreamFormat::LOSSY);
 
         assert_eq!(
             WebPGetFeatures(&data[..16]).err(),
-            Some(VP8StatusCode::VP8_STATUS_NOT_ENOUGH_DATA)
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_NOT_ENOUGH_DATA))
         );
 
         let data = b"\
@@ -1044,7 +1054,7 @@ mod tests {
             K\x00\x00\x00\xD0\x01\x00\x9D\x01*\x03\x00\x02\x00\x02\x00";
         assert_eq!(
             WebPGetFeatures(data).err(),
-            Some(VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR)
+            Some(WebpError::Decode(VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR))
         );
     }
 }
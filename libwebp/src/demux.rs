@@ -0,0 +1,345 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::*;
+use std::slice;
+
+use libwebp_sys as sys;
+use libwebp_sys::{WebPDemuxState, WebPFormatFeature};
+
+use error::WebpError;
+use mux::{webp_data, ContainerFeatures, FourCC, WebPMuxAnimBlend, WebPMuxAnimDispose};
+
+#[allow(non_snake_case)]
+pub fn WebPGetDemuxVersion() -> u32 {
+    (unsafe { sys::WebPGetDemuxVersion() }) as u32
+}
+
+/// A frame of a demuxed file. The bitstream points into the input bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemuxFrame<'a> {
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Display time in milliseconds.
+    pub duration: u32,
+    pub dispose_method: WebPMuxAnimDispose,
+    pub blend_method: WebPMuxAnimBlend,
+    pub has_alpha: bool,
+    /// The `ALPH` and `VP8 `/`VP8L` chunks of the frame, which can be
+    /// decoded as is.
+    pub bitstream: &'a [u8],
+}
+
+/// A parsed WebP file, borrowing the bytes it was created from.
+pub struct Demuxer<'a> {
+    ptr: *mut sys::WebPDemuxer,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Demuxer<'a> {
+    /// Parses a complete WebP file.
+    pub fn new(data: &'a [u8]) -> Result<Self, WebpError> {
+        let data = webp_data(data);
+        let mut state = WebPDemuxState::WEBP_DEMUX_PARSE_ERROR;
+        // partial parsing reports how far it got on truncated input
+        let ptr = unsafe { sys::WebPDemuxPartial(&data, &mut state) };
+        if ptr.is_null() {
+            return Err(WebpError::DemuxParse(state));
+        }
+        let demuxer = Demuxer {
+            ptr,
+            _marker: PhantomData,
+        };
+        if state != WebPDemuxState::WEBP_DEMUX_DONE {
+            return Err(WebpError::DemuxParse(state));
+        }
+        Ok(demuxer)
+    }
+
+    pub fn as_ptr(&self) -> *const sys::WebPDemuxer {
+        self.ptr
+    }
+
+    fn get(&self, feature: WebPFormatFeature) -> u32 {
+        unsafe { sys::WebPDemuxGetI(self.ptr, feature) }
+    }
+
+    pub fn canvas_width(&self) -> u32 {
+        self.get(WebPFormatFeature::WEBP_FF_CANVAS_WIDTH)
+    }
+
+    pub fn canvas_height(&self) -> u32 {
+        self.get(WebPFormatFeature::WEBP_FF_CANVAS_HEIGHT)
+    }
+
+    /// Number of times to play the animation; 0 loops forever. Still images
+    /// report 1.
+    pub fn loop_count(&self) -> u32 {
+        self.get(WebPFormatFeature::WEBP_FF_LOOP_COUNT)
+    }
+
+    /// Background colour, as `0xAARRGGBB`.
+    pub fn background_color(&self) -> u32 {
+        self.get(WebPFormatFeature::WEBP_FF_BACKGROUND_COLOR)
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.get(WebPFormatFeature::WEBP_FF_FRAME_COUNT)
+    }
+
+    /// Returns the flags of the `VP8X` chunk; empty for simple files.
+    pub fn features(&self) -> ContainerFeatures {
+        ContainerFeatures::from_bits_truncate(self.get(WebPFormatFeature::WEBP_FF_FORMAT_FLAGS))
+    }
+
+    /// Returns the `index`-th frame (counting from 0).
+    pub fn frame(&self, index: u32) -> Option<DemuxFrame<'a>> {
+        if index >= self.frame_count() {
+            return None;
+        }
+        let mut iter: sys::WebPIterator = unsafe { mem::zeroed() };
+        // libwebp counts frames from 1
+        if unsafe { sys::WebPDemuxGetFrame(self.ptr, index as c_int + 1, &mut iter) } == 0 {
+            return None;
+        }
+        let frame = DemuxFrame {
+            x_offset: iter.x_offset as u32,
+            y_offset: iter.y_offset as u32,
+            width: iter.width as u32,
+            height: iter.height as u32,
+            duration: iter.duration as u32,
+            dispose_method: iter.dispose_method,
+            blend_method: iter.blend_method,
+            has_alpha: iter.has_alpha != 0,
+            bitstream: unsafe { data_slice(&iter.fragment) },
+        };
+        unsafe {
+            sys::WebPDemuxReleaseIterator(&mut iter);
+        }
+        Some(frame)
+    }
+
+    pub fn frames<'b>(&'b self) -> impl Iterator<Item = DemuxFrame<'a>> + 'b {
+        (0..self.frame_count()).filter_map(move |index| self.frame(index))
+    }
+
+    /// Returns the payloads of all application chunks with the ID, in file order.
+    pub fn unknown_chunks(&self, id: FourCC) -> Vec<&'a [u8]> {
+        self.get_chunks(id.as_bytes())
+    }
+
+    /// Returns the payload of the first application chunk with the ID.
+    pub fn unknown_chunk(&self, id: FourCC) -> Option<&'a [u8]> {
+        self.get_chunk(id.as_bytes())
+    }
+
+    /// Returns the ICC colour profile from the `ICCP` chunk, if any.
+    pub fn icc_profile(&self) -> Option<&'a [u8]> {
+        self.get_chunk(b"ICCP")
+    }
+
+    /// Returns the raw `EXIF` chunk, if any. See `ExifInfo::parse`.
+    pub fn exif(&self) -> Option<&'a [u8]> {
+        self.get_chunk(b"EXIF")
+    }
+
+    /// Returns the XMP metadata packet from the `XMP ` chunk, if any.
+    pub fn xmp(&self) -> Option<&'a [u8]> {
+        self.get_chunk(b"XMP ")
+    }
+
+    fn get_chunks(&self, fourcc: &[u8; 4]) -> Vec<&'a [u8]> {
+        let mut iter: sys::WebPChunkIterator = unsafe { mem::zeroed() };
+        let found = unsafe {
+            sys::WebPDemuxGetChunk(self.ptr, fourcc.as_ptr() as *const c_char, 1, &mut iter)
+        };
+        let mut chunks = Vec::new();
+        if found != 0 {
+            loop {
+                chunks.push(unsafe { data_slice(&iter.chunk) });
+                if unsafe { sys::WebPDemuxNextChunk(&mut iter) } == 0 {
+                    break;
+                }
+            }
+        }
+        unsafe {
+            sys::WebPDemuxReleaseChunkIterator(&mut iter);
+        }
+        chunks
+    }
+
+    fn get_chunk(&self, fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+        self.get_chunks(fourcc).into_iter().next()
+    }
+}
+
+// The demuxer does not copy its input, so the data outlives it.
+unsafe fn data_slice<'a>(data: &sys::WebPData) -> &'a [u8] {
+    if data.bytes.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data.bytes, data.size)
+    }
+}
+
+impl<'a> Drop for Demuxer<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::WebPDemuxDelete(self.ptr);
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Demuxer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Demuxer")
+            .field("canvas_width", &self.canvas_width())
+            .field("canvas_height", &self.canvas_height())
+            .field("frame_count", &self.frame_count())
+            .field("features", &self.features())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use decode::{WebPDecodeRGBA, WebPGetInfo};
+    use mux::{Mux, MuxAnimParams, MuxFrameInfo};
+    use test_utils::{solid_frame, test_cases};
+
+    #[test]
+    fn test_get_demux_version() {
+        assert!(WebPGetDemuxVersion() >= 0x000100);
+    }
+
+    #[test]
+    fn test_demux_still() {
+        for case in test_cases() {
+            let demuxer = Demuxer::new(&case.webp_data).unwrap();
+            let (width, height) = WebPGetInfo(&case.webp_data).unwrap();
+            assert_eq!(
+                (demuxer.canvas_width(), demuxer.canvas_height()),
+                (width, height)
+            );
+            assert_eq!(demuxer.frame_count(), 1);
+            assert_eq!(demuxer.features(), ContainerFeatures::empty());
+            assert_eq!(demuxer.loop_count(), 1);
+
+            let frame = demuxer.frame(0).unwrap();
+            assert_eq!((frame.x_offset, frame.y_offset), (0, 0));
+            assert_eq!((frame.width, frame.height), (width, height));
+            assert_eq!(
+                WebPDecodeRGBA(frame.bitstream).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+            assert!(demuxer.frame(1).is_none());
+            assert_eq!(demuxer.frames().count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_demux_animation() {
+        let red = solid_frame(4, 4, [255, 0, 0, 128]);
+        let green = solid_frame(2, 6, [0, 255, 0, 255]);
+        let mut mux = Mux::new().unwrap();
+        mux.push_frame(&red, &MuxFrameInfo::default()).unwrap();
+        let second = MuxFrameInfo {
+            x_offset: 6,
+            y_offset: 2,
+            duration: 250,
+            dispose_method: WebPMuxAnimDispose::WEBP_MUX_DISPOSE_BACKGROUND,
+            blend_method: WebPMuxAnimBlend::WEBP_MUX_NO_BLEND,
+        };
+        mux.push_frame(&green, &second).unwrap();
+        mux.set_animation_params(&MuxAnimParams {
+            background_color: 0xff20_4060,
+            loop_count: 3,
+        })
+        .unwrap();
+        mux.set_xmp(b"<x:xmpmeta/>").unwrap();
+        let webp = mux.assemble().unwrap();
+
+        let demuxer = Demuxer::new(&webp).unwrap();
+        assert_eq!((demuxer.canvas_width(), demuxer.canvas_height()), (8, 8));
+        assert_eq!(demuxer.loop_count(), 3);
+        assert_eq!(demuxer.background_color(), 0xff20_4060);
+        assert_eq!(demuxer.frame_count(), 2);
+        let features = demuxer.features();
+        assert!(features.has_animation() && features.has_alpha() && features.has_xmp());
+        assert_eq!(
+            features,
+            Mux::from_bytes(&webp).unwrap().features().unwrap()
+        );
+        assert_eq!(demuxer.xmp(), Some(&b"<x:xmpmeta/>"[..]));
+        assert_eq!(demuxer.exif(), None);
+        assert_eq!(demuxer.icc_profile(), None);
+
+        let frames = demuxer.frames().collect::<Vec<_>>();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].has_alpha);
+        assert_eq!((frames[1].x_offset, frames[1].y_offset), (6, 2));
+        assert_eq!((frames[1].width, frames[1].height), (2, 6));
+        assert_eq!(frames[1].duration, 250);
+        assert_eq!(frames[1].dispose_method, second.dispose_method);
+        assert_eq!(frames[1].blend_method, second.blend_method);
+        assert_eq!(
+            WebPDecodeRGBA(frames[1].bitstream).unwrap(),
+            WebPDecodeRGBA(&green).unwrap()
+        );
+
+        // frame views stay valid after the demuxer is gone
+        let first = demuxer.frame(0).unwrap();
+        drop(demuxer);
+        assert_eq!(
+            WebPDecodeRGBA(first.bitstream).unwrap(),
+            WebPDecodeRGBA(&red).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_demux_chunks() {
+        let frame = solid_frame(4, 4, [255, 0, 0, 255]);
+        let mut webp = frame.to_vec();
+        for payload in &[b"one!", b"two!"] {
+            webp.extend_from_slice(b"ORIG\x04\0\0\0");
+            webp.extend_from_slice(&payload[..]);
+        }
+        let riff_size = (webp.len() - 8) as u32;
+        webp[4..8].copy_from_slice(&[
+            riff_size as u8,
+            (riff_size >> 8) as u8,
+            (riff_size >> 16) as u8,
+            (riff_size >> 24) as u8,
+        ]);
+        // unknown chunks are only found in extended files
+        let mut mux = Mux::from_bytes(&webp).unwrap();
+        mux.set_xmp(b"<x/>").unwrap();
+        let webp = mux.assemble().unwrap();
+        let demuxer = Demuxer::new(&webp).unwrap();
+        let origin = FourCC::new(b"ORIG").unwrap();
+        assert_eq!(
+            demuxer.unknown_chunks(origin),
+            vec![&b"one!"[..], &b"two!"[..]]
+        );
+        assert_eq!(demuxer.unknown_chunk(origin), Some(&b"one!"[..]));
+        assert_eq!(demuxer.unknown_chunk(FourCC::new(b"pHsh").unwrap()), None);
+        assert_eq!(demuxer.xmp(), Some(&b"<x/>"[..]));
+    }
+
+    #[test]
+    fn test_demux_errors() {
+        assert_eq!(
+            Demuxer::new(b"").unwrap_err(),
+            WebpError::DemuxParse(WebPDemuxState::WEBP_DEMUX_PARSE_ERROR)
+        );
+        assert!(Demuxer::new(b"RIFF\x04\x00\x00\x00WEBP").is_err());
+        for case in test_cases() {
+            let data = &case.webp_data;
+            assert!(Demuxer::new(&data[..data.len() - 1]).is_err());
+        }
+    }
+}
@@ -1,11 +1,20 @@
+use std::cmp;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
 use std::os::raw::*;
 use std::ptr;
+use std::slice;
 
 use libwebp_sys as sys;
 
 use boxed::WebpBox;
-use error::WebpUnknownError;
-use ffi_utils::{check_int, check_stride};
+use decode::WebPDecodeRGBA;
+use error::WebpError;
+use ffi_utils::{check_dimension, check_int, check_stride};
+
+pub use libwebp_sys::{WebPImageHint, WebPPreset};
 
 macro_rules! check_int {
     ($e:expr) => {
@@ -20,15 +29,16 @@ pub fn WebPEncodeRGB(
     height: u32,
     stride: u32,
     quality_factor: f32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(rgb.len(), width, height, stride, 3);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(rgb.len(), width, height, stride, 3)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeRGB(
             rgb.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             quality_factor as c_float,
             &mut output,
         )
@@ -36,7 +46,7 @@ pub fn WebPEncodeRGB(
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -47,15 +57,16 @@ pub fn WebPEncodeBGR(
     height: u32,
     stride: u32,
     quality_factor: f32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(bgr.len(), width, height, stride, 3);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(bgr.len(), width, height, stride, 3)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeBGR(
             bgr.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             quality_factor as c_float,
             &mut output,
         )
@@ -63,7 +74,7 @@ pub fn WebPEncodeBGR(
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -74,15 +85,16 @@ pub fn WebPEncodeRGBA(
     height: u32,
     stride: u32,
     quality_factor: f32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(rgba.len(), width, height, stride, 4);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(rgba.len(), width, height, stride, 4)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeRGBA(
             rgba.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             quality_factor as c_float,
             &mut output,
         )
@@ -90,7 +102,7 @@ pub fn WebPEncodeRGBA(
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -101,15 +113,16 @@ pub fn WebPEncodeBGRA(
     height: u32,
     stride: u32,
     quality_factor: f32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(bgra.len(), width, height, stride, 4);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(bgra.len(), width, height, stride, 4)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeBGRA(
             bgra.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             quality_factor as c_float,
             &mut output,
         )
@@ -117,7 +130,7 @@ pub fn WebPEncodeBGRA(
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -127,22 +140,23 @@ pub fn WebPEncodeLosslessRGB(
     width: u32,
     height: u32,
     stride: u32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(rgb.len(), width, height, stride, 3);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(rgb.len(), width, height, stride, 3)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeLosslessRGB(
             rgb.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             &mut output,
         )
     };
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -152,22 +166,23 @@ pub fn WebPEncodeLosslessBGR(
     width: u32,
     height: u32,
     stride: u32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(bgr.len(), width, height, stride, 3);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(bgr.len(), width, height, stride, 3)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeLosslessBGR(
             bgr.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             &mut output,
         )
     };
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -177,22 +192,23 @@ pub fn WebPEncodeLosslessRGBA(
     width: u32,
     height: u32,
     stride: u32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(rgba.len(), width, height, stride, 4);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(rgba.len(), width, height, stride, 4)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeLosslessRGBA(
             rgba.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             &mut output,
         )
     };
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
@@ -202,161 +218,2632 @@ pub fn WebPEncodeLosslessBGRA(
     width: u32,
     height: u32,
     stride: u32,
-) -> Result<WebpBox<[u8]>, WebpUnknownError> {
-    check_stride(bgra.len(), width, height, stride, 4);
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(bgra.len(), width, height, stride, 4)?;
     let mut output: *mut u8 = ptr::null_mut();
     let res = unsafe {
         sys::WebPEncodeLosslessBGRA(
             bgra.as_ptr(),
-            check_int!(width),
-            check_int!(height),
-            check_int!(stride),
+            check_int!(width)?,
+            check_int!(height)?,
+            check_int!(stride)?,
             &mut output,
         )
     };
     if res != 0 {
         Ok(unsafe { WebpBox::from_raw_parts(output, res) })
     } else {
-        Err(WebpUnknownError)
+        Err(WebpError::Unknown)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[allow(non_snake_case)]
+pub fn WebPGetEncoderVersion() -> u32 {
+    unsafe { sys::WebPGetEncoderVersion() as u32 }
+}
 
-    use decode::*;
-    use test_utils::*;
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct WebPConfig(sys::WebPConfig);
 
-    fn pngs() -> &'static [Image] {
-        lazy_static! {
-            static ref PNGS: Vec<Image> = {
-                vec![
-                    decode_png(include_bytes!("../data/4.png")),
-                    decode_png(include_bytes!("../data/5.png")),
-                ]
-            };
+/// Compression method for the alpha plane of lossy images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WebPAlphaCompression {
+    None = 0,
+    Lossless = 1,
+}
+
+/// Predictive filtering applied to the alpha plane before compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WebPAlphaFiltering {
+    None = 0,
+    Fast = 1,
+    Best = 2,
+}
+
+impl WebPConfig {
+    pub fn as_ptr(&self) -> *const sys::WebPConfig {
+        &self.0
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut sys::WebPConfig {
+        &mut self.0
+    }
+
+    pub fn lossless(&self) -> bool {
+        self.0.lossless != 0
+    }
+
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.0.lossless = lossless as c_int;
+    }
+
+    pub fn quality(&self) -> f32 {
+        self.0.quality
+    }
+
+    pub fn set_quality(&mut self, quality: f32) {
+        self.0.quality = quality as c_float;
+    }
+
+    pub fn method(&self) -> u32 {
+        self.0.method as u32
+    }
+
+    pub fn set_method(&mut self, method: u32) -> Result<(), WebpError> {
+        self.0.method = check_int!(method)?;
+        Ok(())
+    }
+
+    pub fn image_hint(&self) -> WebPImageHint {
+        self.0.image_hint
+    }
+
+    pub fn set_image_hint(&mut self, image_hint: WebPImageHint) {
+        self.0.image_hint = image_hint;
+    }
+
+    pub fn alpha_compression(&self) -> WebPAlphaCompression {
+        match self.0.alpha_compression {
+            0 => WebPAlphaCompression::None,
+            _ => WebPAlphaCompression::Lossless,
         }
-        &PNGS
     }
-    fn from_webp(webp: &[u8]) -> Image {
-        let (width, height, data) = WebPDecodeRGBA(webp).unwrap();
-        Image::new(ColorType::RGBA, width, height, width * 4, data.to_vec())
+
+    pub fn set_alpha_compression(&mut self, alpha_compression: WebPAlphaCompression) {
+        self.0.alpha_compression = alpha_compression as c_int;
     }
 
-    #[test]
-    fn test_encode_rgb() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::RGB);
-            let webp = WebPEncodeRGB(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-                75.0,
-            )
-            .unwrap();
-            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+    pub fn alpha_filtering(&self) -> WebPAlphaFiltering {
+        match self.0.alpha_filtering {
+            0 => WebPAlphaFiltering::None,
+            1 => WebPAlphaFiltering::Fast,
+            _ => WebPAlphaFiltering::Best,
         }
     }
 
-    #[test]
-    fn test_encode_bgr() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::BGR);
-            let webp = WebPEncodeBGR(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-                75.0,
-            )
-            .unwrap();
-            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+    pub fn set_alpha_filtering(&mut self, alpha_filtering: WebPAlphaFiltering) {
+        self.0.alpha_filtering = alpha_filtering as c_int;
+    }
+
+    pub fn alpha_quality(&self) -> u32 {
+        self.0.alpha_quality as u32
+    }
+
+    /// Sets the alpha plane quality, between 0 (smallest) and 100 (lossless).
+    pub fn set_alpha_quality(&mut self, alpha_quality: u32) -> Result<(), WebpError> {
+        if alpha_quality > 100 {
+            return Err(WebpError::InvalidArgument(format!(
+                "alpha quality {} should be between 0 and 100",
+                alpha_quality,
+            )));
         }
+        self.0.alpha_quality = alpha_quality as c_int;
+        Ok(())
     }
 
-    #[test]
-    fn test_encode_rgba() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::RGBA);
-            let webp = WebPEncodeRGBA(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-                75.0,
-            )
-            .unwrap();
-            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+    /// Reserved by libwebp for a future lossless feature; currently it does
+    /// not change the output.
+    #[cfg(feature = "0.6")]
+    pub fn use_delta_palette(&self) -> bool {
+        self.0.use_delta_palette != 0
+    }
+
+    #[cfg(feature = "0.6")]
+    pub fn set_use_delta_palette(&mut self, use_delta_palette: bool) {
+        self.0.use_delta_palette = use_delta_palette as c_int;
+    }
+
+    #[cfg(feature = "0.5")]
+    pub fn exact(&self) -> bool {
+        self.0.exact != 0
+    }
+
+    #[cfg(feature = "0.5")]
+    pub fn set_exact(&mut self, exact: bool) {
+        self.0.exact = exact as c_int;
+    }
+}
+
+impl fmt::Debug for WebPConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebPConfig")
+            .field("lossless", &self.lossless())
+            .field("quality", &self.quality())
+            .field("method", &self.method())
+            .field("image_hint", &self.image_hint())
+            .field("alpha_compression", &self.alpha_compression())
+            .field("alpha_filtering", &self.alpha_filtering())
+            .field("alpha_quality", &self.alpha_quality())
+            .finish()
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn WebPConfigInit() -> Result<WebPConfig, WebpError> {
+    let mut config = unsafe { mem::zeroed() };
+    let res = unsafe { sys::WebPConfigInit(&mut config) };
+    if res != 0 {
+        Ok(WebPConfig(config))
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn WebPConfigPreset(preset: WebPPreset, quality: f32) -> Result<WebPConfig, WebpError> {
+    let mut config = unsafe { mem::zeroed() };
+    let res = unsafe { sys::WebPConfigPreset(&mut config, preset, quality as c_float) };
+    if res != 0 {
+        Ok(WebPConfig(config))
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+/// Switches `config` to lossless with the effort `level`, between 0 (fastest)
+/// and 9 (smallest output).
+#[cfg(feature = "0.5")]
+#[allow(non_snake_case)]
+pub fn WebPConfigLosslessPreset(config: &mut WebPConfig, level: u32) -> Result<(), WebpError> {
+    if level > 9 {
+        return Err(WebpError::InvalidArgument(format!(
+            "lossless level {} should be between 0 and 9",
+            level,
+        )));
+    }
+    let res = unsafe { sys::WebPConfigLosslessPreset(&mut config.0, level as c_int) };
+    if res != 0 {
+        Ok(())
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn WebPValidateConfig(config: &WebPConfig) -> bool {
+    unsafe { sys::WebPValidateConfig(&config.0) != 0 }
+}
+
+#[repr(transparent)]
+pub struct WebPPicture(sys::WebPPicture);
+
+impl Drop for WebPPicture {
+    fn drop(&mut self) {
+        unsafe {
+            sys::WebPPictureFree(&mut self.0);
         }
     }
+}
 
-    #[test]
-    fn test_encode_bgra() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::BGRA);
-            let webp = WebPEncodeBGRA(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-                75.0,
-            )
-            .unwrap();
-            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+impl WebPPicture {
+    pub fn as_ptr(&self) -> *const sys::WebPPicture {
+        &self.0
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut sys::WebPPicture {
+        &mut self.0
+    }
+
+    pub fn use_argb(&self) -> bool {
+        self.0.use_argb != 0
+    }
+
+    /// Selects the colour mode libwebp works in. Existing pixels are not
+    /// converted, so import pixels after switching.
+    pub fn set_use_argb(&mut self, use_argb: bool) {
+        self.0.use_argb = use_argb as c_int;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.0.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.0.height as u32
+    }
+
+    /// Sets the picture dimension. Pixel data held by the picture is released;
+    /// methods that need pixels fail until new ones are imported.
+    pub fn set_dimension(&mut self, width: u32, height: u32) -> Result<(), WebpError> {
+        check_dimension(width, height)?;
+        unsafe {
+            sys::WebPPictureFree(&mut self.0);
         }
+        self.0.width = width as c_int;
+        self.0.height = height as c_int;
+        Ok(())
     }
 
-    #[test]
-    fn test_encode_lossless_rgb() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::RGB);
-            let webp =
-                WebPEncodeLosslessRGB(&image.data(), image.width(), image.height(), image.stride())
-                    .unwrap();
-            assert_eq!(from_webp(&webp), image);
+    /// Returns true if the picture doesn't own its pixel data.
+    pub fn is_view(&self) -> bool {
+        unsafe { sys::WebPPictureIsView(&self.0) != 0 }
+    }
+
+    // `set_dimension` and `set_use_argb` leave the pixel buffers alone, so
+    // the planes libwebp reads for the current colour mode may be missing.
+    fn check_pixels(&self) -> Result<(), WebpError> {
+        let picture = &self.0;
+        let has_alpha_plane = match picture.colorspace {
+            sys::WebPEncCSP::WEBP_YUV420A => !picture.a.is_null(),
+            sys::WebPEncCSP::WEBP_YUV420 => true,
+        };
+        let allocated = if self.use_argb() {
+            !picture.argb.is_null()
+        } else {
+            !picture.y.is_null() && !picture.u.is_null() && !picture.v.is_null() && has_alpha_plane
+        };
+        if allocated {
+            Ok(())
+        } else {
+            Err(WebpError::InvalidArgument(format!(
+                "picture {}x{} has no {} pixels; import some first",
+                self.width(),
+                self.height(),
+                if self.use_argb() { "ARGB" } else { "YUV" },
+            )))
         }
     }
 
-    #[test]
-    fn test_encode_lossless_bgr() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::BGR);
-            let webp =
-                WebPEncodeLosslessBGR(&image.data(), image.width(), image.height(), image.stride())
-                    .unwrap();
-            assert_eq!(from_webp(&webp), image);
+    fn check_rect(
+        &self,
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(c_int, c_int, c_int, c_int), WebpError> {
+        let inside = match (left.checked_add(width), top.checked_add(height)) {
+            (Some(right), Some(bottom)) => right <= self.width() && bottom <= self.height(),
+            _ => false,
+        };
+        if width == 0 || height == 0 || !inside {
+            return Err(WebpError::InvalidArgument(format!(
+                "rectangle {}x{}+{}+{} should be inside the picture {}x{}",
+                width,
+                height,
+                left,
+                top,
+                self.width(),
+                self.height(),
+            )));
         }
+        Ok((left as c_int, top as c_int, width as c_int, height as c_int))
     }
 
-    #[test]
-    fn test_encode_lossless_rgba() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::RGBA);
-            let webp = WebPEncodeLosslessRGBA(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-            )
-            .unwrap();
-            assert_eq!(from_webp(&webp), image);
+    /// Creates a view of the rectangle without copying pixels.
+    ///
+    /// For YUV pictures, `left` and `top` are rounded down to even numbers.
+    /// The view borrows the picture mutably because the encoder may
+    /// clean up transparent areas in place.
+    pub fn view<'a>(
+        &'a mut self,
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<WebPPictureView<'a>, WebpError> {
+        self.check_pixels()?;
+        let (left, top, width, height) = self.check_rect(left, top, width, height)?;
+        let mut view = unsafe { mem::zeroed() };
+        let res = unsafe { sys::WebPPictureView(&self.0, left, top, width, height, &mut view) };
+        if res != 0 {
+            Ok(WebPPictureView(WebPPicture(view), PhantomData))
+        } else {
+            Err(WebpError::Unknown)
         }
     }
 
-    #[test]
-    fn test_encode_lossless_bgra() {
-        for image in pngs() {
-            let image = image.convert_auto_stride(ColorType::BGRA);
-            let webp = WebPEncodeLosslessBGRA(
-                &image.data(),
-                image.width(),
-                image.height(),
-                image.stride(),
-            )
-            .unwrap();
-            assert_eq!(from_webp(&webp), image);
+    /// Crops the picture in place. The pixel data is copied into a new buffer.
+    ///
+    /// For YUV pictures, `left` and `top` are rounded down to even numbers.
+    pub fn crop(&mut self, left: u32, top: u32, width: u32, height: u32) -> Result<(), WebpError> {
+        self.check_pixels()?;
+        let (left, top, width, height) = self.check_rect(left, top, width, height)?;
+        let res = unsafe { sys::WebPPictureCrop(&mut self.0, left, top, width, height) };
+        if res != 0 {
+            Ok(())
+        } else {
+            Err(WebpError::from_encoding_error(self.0.error_code))
+        }
+    }
+
+    /// Rescales the picture with libwebp's resampler.
+    ///
+    /// If either `width` or `height` is zero, it is computed from the other
+    /// one so that the aspect ratio is kept.
+    /// Views get a buffer of their own; the parent picture is left untouched.
+    pub fn rescale(&mut self, width: u32, height: u32) -> Result<(), WebpError> {
+        self.check_pixels()?;
+        let (width, height) = scaled_dimension(self.width(), self.height(), width, height)?;
+        check_dimension(width, height)?;
+        let res = unsafe { sys::WebPPictureRescale(&mut self.0, width as c_int, height as c_int) };
+        if res != 0 {
+            Ok(())
+        } else {
+            Err(WebpError::from_encoding_error(self.0.error_code))
+        }
+    }
+
+    /// Shrinks the picture to fit within `max_width` x `max_height`, keeping the aspect ratio.
+    ///
+    /// The picture is left as is if it already fits.
+    pub fn rescale_to_fit(&mut self, max_width: u32, max_height: u32) -> Result<(), WebpError> {
+        check_dimension(max_width, max_height)?;
+        let (width, height) = (self.width() as u64, self.height() as u64);
+        if width <= max_width as u64 && height <= max_height as u64 {
+            return Ok(());
+        }
+        if width * max_height as u64 > height * max_width as u64 {
+            self.rescale(max_width, 0)
+        } else {
+            self.rescale(0, max_height)
+        }
+    }
+
+    /// Returns true if the picture has any pixel that isn't fully opaque.
+    ///
+    /// A picture without pixels has no transparency.
+    pub fn has_transparency(&self) -> bool {
+        if self.check_pixels().is_err() {
+            return false;
+        }
+        unsafe { sys::WebPPictureHasTransparency(&self.0) != 0 }
+    }
+
+    /// Blends the picture onto a background colour given as `0xRRGGBB`,
+    /// making every pixel fully opaque. The upper 8 bits are ignored.
+    pub fn blend_alpha(&mut self, background_rgb: u32) -> Result<(), WebpError> {
+        self.check_pixels()?;
+        unsafe {
+            sys::WebPBlendAlpha(&mut self.0, background_rgb);
+        }
+        Ok(())
+    }
+
+    /// Smoothens the colour samples under fully transparent areas to help compression.
+    /// Alpha values are not changed.
+    pub fn cleanup_transparent_area(&mut self) -> Result<(), WebpError> {
+        self.check_pixels()?;
+        unsafe {
+            sys::WebPCleanupTransparentArea(&mut self.0);
         }
+        Ok(())
+    }
+}
+
+// Same rounding as `WebPRescalerGetScaledDimensions`, which isn't available before libwebp-0.6.
+fn scaled_dimension(
+    src_width: u32,
+    src_height: u32,
+    width: u32,
+    height: u32,
+) -> Result<(u32, u32), WebpError> {
+    fn scale(a: u32, b: u32, c: u32) -> u32 {
+        let (a, b, c) = (a as u64, b as u64, c as u64);
+        cmp::max(
+            1,
+            cmp::min((a * b + c / 2) / c, u64::from(u32::max_value())),
+        ) as u32
+    }
+    if src_width == 0 || src_height == 0 || (width == 0 && height == 0) {
+        return Err(WebpError::InvalidArgument(format!(
+            "cannot rescale {}x{} to {}x{}",
+            src_width, src_height, width, height,
+        )));
+    }
+    let scaled_width = if width == 0 {
+        scale(src_width, height, src_height)
+    } else {
+        width
+    };
+    let scaled_height = if height == 0 {
+        scale(src_height, width, src_width)
+    } else {
+        height
+    };
+    Ok((scaled_width, scaled_height))
+}
+
+/// A picture sharing pixels with another picture.
+///
+/// The view only dereferences to `&WebPPicture`: a `&mut WebPPicture` could be
+/// swapped out of the view and outlive the pixels it points to. The mutating
+/// operations are provided as methods instead.
+pub struct WebPPictureView<'a>(WebPPicture, PhantomData<&'a mut WebPPicture>);
+
+impl<'a> WebPPictureView<'a> {
+    /// Same as `WebPEncode`.
+    pub fn encode(&mut self, config: &WebPConfig) -> Result<WebpBox<[u8]>, WebpError> {
+        WebPEncode(config, &mut self.0)
+    }
+
+    /// Same as `WebPEncodeWithStats`.
+    pub fn encode_with_stats(
+        &mut self,
+        config: &WebPConfig,
+    ) -> Result<(WebpBox<[u8]>, WebPAuxStats), WebpError> {
+        WebPEncodeWithStats(config, &mut self.0)
+    }
+
+    /// Same as `WebPPicture::crop`. The view then owns its pixels.
+    pub fn crop(&mut self, left: u32, top: u32, width: u32, height: u32) -> Result<(), WebpError> {
+        self.0.crop(left, top, width, height)
+    }
+
+    /// Same as `WebPPicture::rescale`. The view then owns its pixels.
+    pub fn rescale(&mut self, width: u32, height: u32) -> Result<(), WebpError> {
+        self.0.rescale(width, height)
+    }
+
+    /// Same as `WebPPicture::rescale_to_fit`.
+    pub fn rescale_to_fit(&mut self, max_width: u32, max_height: u32) -> Result<(), WebpError> {
+        self.0.rescale_to_fit(max_width, max_height)
+    }
+
+    /// Same as `WebPPicture::blend_alpha`. The parent picture is modified too.
+    pub fn blend_alpha(&mut self, background_rgb: u32) -> Result<(), WebpError> {
+        self.0.blend_alpha(background_rgb)
+    }
+
+    /// Same as `WebPPicture::cleanup_transparent_area`. The parent picture is modified too.
+    pub fn cleanup_transparent_area(&mut self) -> Result<(), WebpError> {
+        self.0.cleanup_transparent_area()
+    }
+}
+
+impl<'a> Deref for WebPPictureView<'a> {
+    type Target = WebPPicture;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> fmt::Debug for WebPPictureView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("WebPPictureView").field(&self.0).finish()
+    }
+}
+
+impl fmt::Debug for WebPPicture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebPPicture")
+            .field("use_argb", &self.use_argb())
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .finish()
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureInit() -> Result<WebPPicture, WebpError> {
+    let mut picture = unsafe { mem::zeroed() };
+    let res = unsafe { sys::WebPPictureInit(&mut picture) };
+    if res != 0 {
+        Ok(WebPPicture(picture))
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+/// Copies the picture into a newly allocated one. Views become owned pictures.
+#[allow(non_snake_case)]
+pub fn WebPPictureCopy(src: &WebPPicture) -> Result<WebPPicture, WebpError> {
+    src.check_pixels()?;
+    let mut dst = WebPPicture(unsafe { mem::zeroed() });
+    let res = unsafe { sys::WebPPictureCopy(&src.0, &mut dst.0) };
+    if res != 0 {
+        Ok(dst)
+    } else {
+        Err(WebpError::from_encoding_error(dst.0.error_code))
+    }
+}
+
+fn picture_import(
+    picture: &mut WebPPicture,
+    data: &[u8],
+    stride: u32,
+    bpp: u32,
+    import: unsafe extern "C" fn(*mut sys::WebPPicture, *const u8, c_int) -> c_int,
+) -> Result<(), WebpError> {
+    check_stride(data.len(), picture.width(), picture.height(), stride, bpp)?;
+    let res = unsafe { import(&mut picture.0, data.as_ptr(), check_int!(stride)?) };
+    if res != 0 {
+        Ok(())
+    } else {
+        Err(WebpError::from_encoding_error(picture.0.error_code))
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportRGB(
+    picture: &mut WebPPicture,
+    rgb: &[u8],
+    rgb_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, rgb, rgb_stride, 3, sys::WebPPictureImportRGB)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportRGBA(
+    picture: &mut WebPPicture,
+    rgba: &[u8],
+    rgba_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, rgba, rgba_stride, 4, sys::WebPPictureImportRGBA)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportRGBX(
+    picture: &mut WebPPicture,
+    rgbx: &[u8],
+    rgbx_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, rgbx, rgbx_stride, 4, sys::WebPPictureImportRGBX)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportBGR(
+    picture: &mut WebPPicture,
+    bgr: &[u8],
+    bgr_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, bgr, bgr_stride, 3, sys::WebPPictureImportBGR)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportBGRA(
+    picture: &mut WebPPicture,
+    bgra: &[u8],
+    bgra_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, bgra, bgra_stride, 4, sys::WebPPictureImportBGRA)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPPictureImportBGRX(
+    picture: &mut WebPPicture,
+    bgrx: &[u8],
+    bgrx_stride: u32,
+) -> Result<(), WebpError> {
+    picture_import(picture, bgrx, bgrx_stride, 4, sys::WebPPictureImportBGRX)
+}
+
+fn unpremultiply(color: u8, alpha: u8) -> u8 {
+    match alpha {
+        0 => 0,
+        255 => color,
+        _ => {
+            // Premultiplied colours never exceed alpha; clamp instead of
+            // amplifying rounding noise of nearly transparent pixels.
+            let color = u32::from(cmp::min(color, alpha));
+            let alpha = u32::from(alpha);
+            ((color * 255 + alpha / 2) / alpha) as u8
+        }
+    }
+}
+
+fn import_premultiplied(
+    picture: &mut WebPPicture,
+    data: &[u8],
+    stride: u32,
+    import: unsafe extern "C" fn(*mut sys::WebPPicture, *const u8, c_int) -> c_int,
+) -> Result<(), WebpError> {
+    check_dimension(picture.width(), picture.height())?;
+    check_stride(data.len(), picture.width(), picture.height(), stride, 4)?;
+    let row_len = picture.width() as usize * 4;
+    let mut straight = Vec::with_capacity(row_len * picture.height() as usize);
+    for row in data.chunks(stride as usize).take(picture.height() as usize) {
+        for pixel in row[..row_len].chunks(4) {
+            let alpha = pixel[3];
+            straight.extend_from_slice(&[
+                unpremultiply(pixel[0], alpha),
+                unpremultiply(pixel[1], alpha),
+                unpremultiply(pixel[2], alpha),
+                alpha,
+            ]);
+        }
+    }
+    picture_import(picture, &straight, row_len as u32, 4, import)
+}
+
+/// Same as `WebPPictureImportRGBA`, but for colours premultiplied by alpha.
+///
+/// Fully transparent pixels become transparent black.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportPremultipliedRGBA(
+    picture: &mut WebPPicture,
+    rgba: &[u8],
+    rgba_stride: u32,
+) -> Result<(), WebpError> {
+    import_premultiplied(picture, rgba, rgba_stride, sys::WebPPictureImportRGBA)
+}
+
+/// Same as `WebPPictureImportBGRA`, but for colours premultiplied by alpha.
+///
+/// Fully transparent pixels become transparent black.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportPremultipliedBGRA(
+    picture: &mut WebPPicture,
+    bgra: &[u8],
+    bgra_stride: u32,
+) -> Result<(), WebpError> {
+    import_premultiplied(picture, bgra, bgra_stride, sys::WebPPictureImportBGRA)
+}
+
+/// Transfer function of the samples passed to the high bit depth importers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WebPTransferFunction {
+    /// Linear light; colour channels are gamma-encoded to sRGB on import.
+    Linear,
+    /// Already sRGB-encoded; samples are only rescaled.
+    SRGB,
+}
+
+/// How `WebPPictureImportRGBA16` and `WebPPictureImportRGBAF32` reduce
+/// samples to 8 bits.
+///
+/// Alpha is always treated as linear coverage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebPDepthConversion {
+    pub transfer: WebPTransferFunction,
+    /// Applies an ordered dither instead of plain rounding, which avoids
+    /// banding in smooth gradients.
+    pub dithering: bool,
+}
+
+impl Default for WebPDepthConversion {
+    fn default() -> Self {
+        Self {
+            transfer: WebPTransferFunction::SRGB,
+            dithering: false,
+        }
+    }
+}
+
+// 4x4 Bayer matrix.
+const DITHER_MATRIX: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Maps `value` in [0, 1] to 8 bits. NaN becomes 0.
+fn quantize_sample(value: f32, x: usize, y: usize, dithering: bool) -> u8 {
+    let offset = if dithering {
+        (f32::from(DITHER_MATRIX[y & 3][x & 3]) + 0.5) / 16.0 - 0.5
+    } else {
+        0.0
+    };
+    // `max` maps NaN to 0, and the cast truncates (the sum is never negative)
+    (value.max(0.0).min(1.0) * 255.0 + 0.5 + offset) as u8
+}
+
+fn import_high_depth<T: Copy>(
+    picture: &mut WebPPicture,
+    rgba: &[T],
+    rgba_stride: u32,
+    conversion: &WebPDepthConversion,
+    to_unit: fn(T) -> f32,
+) -> Result<(), WebpError> {
+    let (width, height) = (picture.width() as usize, picture.height() as usize);
+    check_stride(
+        rgba.len(),
+        picture.width(),
+        picture.height(),
+        rgba_stride,
+        4,
+    )?;
+    let mut converted = vec![0; width * height * 4];
+    for y in 0..height {
+        let src = &rgba[y * rgba_stride as usize..][..width * 4];
+        let dst = &mut converted[y * width * 4..][..width * 4];
+        for x in 0..width {
+            for c in 0..4 {
+                let mut value = to_unit(src[x * 4 + c]);
+                if c < 3 && conversion.transfer == WebPTransferFunction::Linear {
+                    value = linear_to_srgb(value.max(0.0));
+                }
+                dst[x * 4 + c] = quantize_sample(value, x, y, conversion.dithering);
+            }
+        }
+    }
+    WebPPictureImportRGBA(picture, &converted, width as u32 * 4)
+}
+
+/// Imports 16-bit RGBA samples. `rgba_stride` is counted in `u16`s.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportRGBA16(
+    picture: &mut WebPPicture,
+    rgba: &[u16],
+    rgba_stride: u32,
+    conversion: &WebPDepthConversion,
+) -> Result<(), WebpError> {
+    import_high_depth(picture, rgba, rgba_stride, conversion, |v| {
+        f32::from(v) / 65535.0
+    })
+}
+
+/// Imports floating point RGBA samples in [0, 1]; values outside are
+/// clamped. `rgba_stride` is counted in `f32`s.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportRGBAF32(
+    picture: &mut WebPPicture,
+    rgba: &[f32],
+    rgba_stride: u32,
+    conversion: &WebPDepthConversion,
+) -> Result<(), WebpError> {
+    import_high_depth(picture, rgba, rgba_stride, conversion, |v| v)
+}
+
+fn encode_imported<F>(
+    config: &WebPConfig,
+    width: u32,
+    height: u32,
+    import: F,
+) -> Result<WebpBox<[u8]>, WebpError>
+where
+    F: FnOnce(&mut WebPPicture) -> Result<(), WebpError>,
+{
+    let mut picture = WebPPictureInit()?;
+    picture.set_use_argb(config.lossless());
+    picture.set_dimension(width, height)?;
+    import(&mut picture)?;
+    WebPEncode(config, &mut picture)
+}
+
+/// Encodes 16-bit RGBA samples with `config`. See `WebPPictureImportRGBA16`.
+#[allow(non_snake_case)]
+pub fn WebPEncodeRGBA16(
+    config: &WebPConfig,
+    rgba: &[u16],
+    width: u32,
+    height: u32,
+    stride: u32,
+    conversion: &WebPDepthConversion,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_imported(config, width, height, |picture| {
+        WebPPictureImportRGBA16(picture, rgba, stride, conversion)
+    })
+}
+
+/// Encodes floating point RGBA samples with `config`. See
+/// `WebPPictureImportRGBAF32`.
+#[allow(non_snake_case)]
+pub fn WebPEncodeRGBAF32(
+    config: &WebPConfig,
+    rgba: &[f32],
+    width: u32,
+    height: u32,
+    stride: u32,
+    conversion: &WebPDepthConversion,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_imported(config, width, height, |picture| {
+        WebPPictureImportRGBAF32(picture, rgba, stride, conversion)
+    })
+}
+
+// Same as VP8RGBToY() for r = g = b.
+fn gray_to_y(gray: u8) -> u8 {
+    ((56318 * u32::from(gray) + (16 << 16) + (1 << 15)) >> 16) as u8
+}
+
+fn import_gray(
+    picture: &mut WebPPicture,
+    data: &[u8],
+    stride: u32,
+    bpp: u32,
+) -> Result<(), WebpError> {
+    check_stride(data.len(), picture.width(), picture.height(), stride, bpp)?;
+    let (width, height) = (picture.width() as usize, picture.height() as usize);
+    let (stride, bpp) = (stride as usize, bpp as usize);
+    let has_alpha = bpp == 2;
+    if !picture.use_argb() {
+        picture.0.colorspace = if has_alpha {
+            sys::WebPEncCSP::WEBP_YUV420A
+        } else {
+            sys::WebPEncCSP::WEBP_YUV420
+        };
+    }
+    if unsafe { sys::WebPPictureAlloc(&mut picture.0) } == 0 {
+        return Err(WebpError::from_encoding_error(picture.0.error_code));
+    }
+    let pic = &picture.0;
+    for (y, row) in data.chunks(stride).take(height).enumerate() {
+        let row = &row[..width * bpp];
+        if pic.use_argb != 0 {
+            let argb = unsafe {
+                slice::from_raw_parts_mut(
+                    pic.argb.offset(y as isize * pic.argb_stride as isize),
+                    width,
+                )
+            };
+            for (dst, src) in argb.iter_mut().zip(row.chunks(bpp)) {
+                let alpha = if has_alpha { src[1] } else { 0xff };
+                *dst = (u32::from(alpha) << 24) | (u32::from(src[0]) * 0x01_0101);
+            }
+        } else {
+            let luma = unsafe {
+                slice::from_raw_parts_mut(pic.y.offset(y as isize * pic.y_stride as isize), width)
+            };
+            for (dst, src) in luma.iter_mut().zip(row.chunks(bpp)) {
+                *dst = gray_to_y(src[0]);
+            }
+            if has_alpha {
+                let alpha = unsafe {
+                    slice::from_raw_parts_mut(
+                        pic.a.offset(y as isize * pic.a_stride as isize),
+                        width,
+                    )
+                };
+                for (dst, src) in alpha.iter_mut().zip(row.chunks(bpp)) {
+                    *dst = src[1];
+                }
+            }
+        }
+    }
+    if pic.use_argb == 0 {
+        // neutral chroma
+        for y in 0..(height + 1) / 2 {
+            let offset = y as isize * pic.uv_stride as isize;
+            unsafe {
+                ptr::write_bytes(pic.u.offset(offset), 128, (width + 1) / 2);
+                ptr::write_bytes(pic.v.offset(offset), 128, (width + 1) / 2);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Imports 8-bit grayscale samples.
+///
+/// YUV pictures get the luma plane filled directly, with neutral chroma.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportL8(
+    picture: &mut WebPPicture,
+    gray: &[u8],
+    gray_stride: u32,
+) -> Result<(), WebpError> {
+    import_gray(picture, gray, gray_stride, 1)
+}
+
+/// Imports interleaved 8-bit grayscale and alpha samples.
+#[allow(non_snake_case)]
+pub fn WebPPictureImportLA8(
+    picture: &mut WebPPicture,
+    gray_alpha: &[u8],
+    gray_alpha_stride: u32,
+) -> Result<(), WebpError> {
+    import_gray(picture, gray_alpha, gray_alpha_stride, 2)
+}
+
+fn encode_gray(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    bpp: u32,
+    quality_factor: f32,
+    lossless: bool,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    check_dimension(width, height)?;
+    check_stride(data.len(), width, height, stride, bpp)?;
+    let mut config = WebPConfigPreset(WebPPreset::WEBP_PRESET_DEFAULT, quality_factor)?;
+    config.set_lossless(lossless);
+    encode_imported(&config, width, height, |picture| {
+        import_gray(picture, data, stride, bpp)
+    })
+}
+
+#[allow(non_snake_case)]
+pub fn WebPEncodeL8(
+    gray: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    quality_factor: f32,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_gray(gray, width, height, stride, 1, quality_factor, false)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPEncodeLA8(
+    gray_alpha: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    quality_factor: f32,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_gray(gray_alpha, width, height, stride, 2, quality_factor, false)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPEncodeLosslessL8(
+    gray: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_gray(gray, width, height, stride, 1, 70.0, true)
+}
+
+#[allow(non_snake_case)]
+pub fn WebPEncodeLosslessLA8(
+    gray_alpha: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    encode_gray(gray_alpha, width, height, stride, 2, 70.0, true)
+}
+
+/// Encodes the picture with `WebPMemoryWrite` as the writer.
+///
+/// The `writer` and `custom_ptr` fields of the picture are used only during the call.
+#[allow(non_snake_case)]
+pub fn WebPEncode(
+    config: &WebPConfig,
+    picture: &mut WebPPicture,
+) -> Result<WebpBox<[u8]>, WebpError> {
+    let mut writer: sys::WebPMemoryWriter = unsafe { mem::zeroed() };
+    unsafe {
+        sys::WebPMemoryWriterInit(&mut writer);
+    }
+    picture.0.writer = Some(memory_write);
+    picture.0.custom_ptr = &mut writer as *mut sys::WebPMemoryWriter as *mut c_void;
+    let res = unsafe { sys::WebPEncode(&config.0, &mut picture.0) };
+    picture.0.writer = None;
+    picture.0.custom_ptr = ptr::null_mut();
+    let output = if !writer.mem.is_null() {
+        Some(unsafe { WebpBox::from_raw_parts(writer.mem, writer.size) })
+    } else {
+        None
+    };
+    match output {
+        Some(output) if res != 0 => Ok(output),
+        _ => Err(WebpError::from_encoding_error(picture.0.error_code)),
+    }
+}
+
+// `WebPWriterFunction` is a safe fn pointer, so wrap the unsafe import.
+extern "C" fn memory_write(
+    data: *const u8,
+    data_size: usize,
+    picture: *const sys::WebPPicture,
+) -> c_int {
+    unsafe { sys::WebPMemoryWrite(data, data_size, picture) }
+}
+
+/// Reusable encoder for many images with the same settings.
+///
+/// Unlike the one-shot `WebPEncode*` functions, the config is validated once
+/// and the output buffer is kept between calls, growing only when an image
+/// needs more room than any before it.
+#[cfg(feature = "0.5")]
+pub struct Encoder {
+    config: WebPConfig,
+    picture: WebPPicture,
+    writer: sys::WebPMemoryWriter,
+}
+
+#[cfg(feature = "0.5")]
+impl Encoder {
+    pub fn new(config: WebPConfig) -> Result<Self, WebpError> {
+        check_config(&config)?;
+        let mut picture = WebPPictureInit()?;
+        picture.set_use_argb(config.lossless());
+        let mut writer = unsafe { mem::zeroed() };
+        unsafe {
+            sys::WebPMemoryWriterInit(&mut writer);
+        }
+        Ok(Self {
+            config,
+            picture,
+            writer,
+        })
+    }
+
+    pub fn config(&self) -> &WebPConfig {
+        &self.config
+    }
+
+    /// Replaces the config. An invalid config is rejected and the current one is kept.
+    pub fn set_config(&mut self, config: WebPConfig) -> Result<(), WebpError> {
+        check_config(&config)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Encodes RGB samples. The output is valid until the next call.
+    pub fn encode_rgb(
+        &mut self,
+        rgb: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) -> Result<&[u8], WebpError> {
+        self.encode(width, height, |picture| {
+            WebPPictureImportRGB(picture, rgb, stride)
+        })
+    }
+
+    /// Encodes RGBA samples. The output is valid until the next call.
+    pub fn encode_rgba(
+        &mut self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) -> Result<&[u8], WebpError> {
+        self.encode(width, height, |picture| {
+            WebPPictureImportRGBA(picture, rgba, stride)
+        })
+    }
+
+    fn encode<F>(&mut self, width: u32, height: u32, import: F) -> Result<&[u8], WebpError>
+    where
+        F: FnOnce(&mut WebPPicture) -> Result<(), WebpError>,
+    {
+        check_dimension(width, height)?;
+        let picture = &mut self.picture;
+        picture.set_use_argb(self.config.lossless());
+        if picture.width() != width || picture.height() != height {
+            picture.set_dimension(width, height)?;
+        }
+        import(picture)?;
+        rewind_memory_writer(&mut self.writer);
+        picture.0.writer = Some(memory_write);
+        picture.0.custom_ptr = &mut self.writer as *mut sys::WebPMemoryWriter as *mut c_void;
+        let res = unsafe { sys::WebPEncode(&self.config.0, &mut picture.0) };
+        picture.0.writer = None;
+        picture.0.custom_ptr = ptr::null_mut();
+        if res != 0 && !self.writer.mem.is_null() {
+            Ok(unsafe { slice::from_raw_parts(self.writer.mem, self.writer.size) })
+        } else {
+            Err(WebpError::from_encoding_error(picture.0.error_code))
+        }
+    }
+
+    /// Releases the picture and the output buffer.
+    pub fn reset(&mut self) {
+        unsafe {
+            sys::WebPPictureFree(&mut self.picture.0);
+            sys::WebPMemoryWriterClear(&mut self.writer);
+        }
+        self.picture.0.width = 0;
+        self.picture.0.height = 0;
+    }
+}
+
+#[cfg(feature = "0.5")]
+fn check_config(config: &WebPConfig) -> Result<(), WebpError> {
+    if WebPValidateConfig(config) {
+        Ok(())
+    } else {
+        Err(WebpError::Encode(
+            sys::WebPEncodingError::VP8_ENC_ERROR_INVALID_CONFIGURATION,
+        ))
+    }
+}
+
+/// Empties the writer but keeps its buffer.
+///
+/// `WebPMemoryWriterClear` is not used here because it frees the buffer,
+/// so every image would allocate a new one. `WebPMemoryWrite` appends at
+/// `size` and reallocates only when the data would exceed `max_size`, so
+/// the buffer stays valid for the next picture. `reset` and `drop` still
+/// release it with `WebPMemoryWriterClear`.
+#[cfg(feature = "0.5")]
+fn rewind_memory_writer(writer: &mut sys::WebPMemoryWriter) {
+    writer.size = 0;
+}
+
+#[cfg(feature = "0.5")]
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        unsafe {
+            sys::WebPMemoryWriterClear(&mut self.writer);
+        }
+    }
+}
+
+#[cfg(feature = "0.5")]
+impl fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encoder")
+            .field("config", &self.config)
+            .field("capacity", &self.writer.max_size)
+            .finish()
+    }
+}
+
+pub const WEBP_LOSSLESS_PREDICTOR: u32 = 1;
+pub const WEBP_LOSSLESS_CROSS_COLOR: u32 = 2;
+pub const WEBP_LOSSLESS_SUBTRACT_GREEN: u32 = 4;
+pub const WEBP_LOSSLESS_COLOR_INDEXING: u32 = 8;
+
+/// Statistics collected by `WebPEncodeWithStats`.
+pub struct WebPAuxStats(sys::WebPAuxStats);
+
+impl WebPAuxStats {
+    pub fn as_ptr(&self) -> *const sys::WebPAuxStats {
+        &self.0
+    }
+
+    /// Total size of the output in bytes.
+    pub fn coded_size(&self) -> u32 {
+        self.0.coded_size as u32
+    }
+
+    /// Size of the compressed alpha plane in bytes.
+    pub fn alpha_data_size(&self) -> u32 {
+        self.0.alpha_data_size as u32
+    }
+
+    /// Transforms used by the lossless encoder, a combination of the
+    /// `WEBP_LOSSLESS_*` bits.
+    pub fn lossless_features(&self) -> u32 {
+        self.0.lossless_features
+    }
+
+    /// Whether the lossless encoder used a colour palette.
+    pub fn uses_palette(&self) -> bool {
+        self.0.lossless_features & WEBP_LOSSLESS_COLOR_INDEXING != 0
+    }
+
+    /// Number of colours in the palette, if one was used.
+    pub fn palette_size(&self) -> u32 {
+        self.0.palette_size as u32
+    }
+
+    /// Number of bits for colour cache lookup; 0 when the cache is off.
+    pub fn cache_bits(&self) -> u32 {
+        self.0.cache_bits as u32
+    }
+
+    pub fn histogram_bits(&self) -> u32 {
+        self.0.histogram_bits as u32
+    }
+
+    pub fn transform_bits(&self) -> u32 {
+        self.0.transform_bits as u32
+    }
+}
+
+impl fmt::Debug for WebPAuxStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebPAuxStats")
+            .field("coded_size", &self.coded_size())
+            .field("alpha_data_size", &self.alpha_data_size())
+            .field("lossless_features", &self.lossless_features())
+            .field("palette_size", &self.palette_size())
+            .field("cache_bits", &self.cache_bits())
+            .finish()
+    }
+}
+
+/// Same as `WebPEncode`, but also returns the encoder statistics.
+#[allow(non_snake_case)]
+pub fn WebPEncodeWithStats(
+    config: &WebPConfig,
+    picture: &mut WebPPicture,
+) -> Result<(WebpBox<[u8]>, WebPAuxStats), WebpError> {
+    let mut stats = WebPAuxStats(unsafe { mem::zeroed() });
+    picture.0.stats = &mut stats.0;
+    let res = WebPEncode(config, picture);
+    picture.0.stats = ptr::null_mut();
+    res.map(|output| (output, stats))
+}
+
+/// Settings for `WebPEncodeLosslessRGBAWithOptions`.
+#[cfg(feature = "0.5")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebPLosslessOptions {
+    /// Effort level as in `WebPConfigLosslessPreset`, between 0 and 9.
+    pub level: u32,
+    #[cfg(feature = "0.6")]
+    pub use_delta_palette: bool,
+}
+
+#[cfg(feature = "0.5")]
+impl Default for WebPLosslessOptions {
+    /// Level 6, which matches the settings of `WebPConfigInit`.
+    fn default() -> Self {
+        Self {
+            level: 6,
+            #[cfg(feature = "0.6")]
+            use_delta_palette: false,
+        }
+    }
+}
+
+/// Losslessly encodes RGBA samples with the given effort level.
+#[cfg(feature = "0.5")]
+#[allow(non_snake_case)]
+pub fn WebPEncodeLosslessRGBAWithOptions(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    options: &WebPLosslessOptions,
+) -> Result<(WebpBox<[u8]>, WebPAuxStats), WebpError> {
+    check_dimension(width, height)?;
+    check_stride(rgba.len(), width, height, stride, 4)?;
+    let mut config = WebPConfigInit()?;
+    WebPConfigLosslessPreset(&mut config, options.level)?;
+    #[cfg(feature = "0.6")]
+    config.set_use_delta_palette(options.use_delta_palette);
+    let mut picture = WebPPictureInit()?;
+    picture.set_use_argb(true);
+    picture.set_dimension(width, height)?;
+    WebPPictureImportRGBA(&mut picture, rgba, stride)?;
+    WebPEncodeWithStats(&config, &mut picture)
+}
+
+/// Kind of per-macroblock information for `WebPEncodeWithExtraInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WebPExtraInfoType {
+    IntraType = 1,
+    Segment = 2,
+    Quant = 3,
+    Intra16Mode = 4,
+    ChromaMode = 5,
+    BitCost = 6,
+    Distortion = 7,
+}
+
+/// Macroblock map filled by the lossy encoder, one byte per 16x16 block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebPExtraInfo {
+    info_type: WebPExtraInfoType,
+    mb_width: u32,
+    mb_height: u32,
+    data: Vec<u8>,
+}
+
+impl WebPExtraInfo {
+    pub fn info_type(&self) -> WebPExtraInfoType {
+        self.info_type
+    }
+
+    /// Width in macroblocks.
+    pub fn mb_width(&self) -> u32 {
+        self.mb_width
+    }
+
+    /// Height in macroblocks.
+    pub fn mb_height(&self) -> u32 {
+        self.mb_height
+    }
+
+    /// Row-major values, `mb_width * mb_height` bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get(&self, mb_x: u32, mb_y: u32) -> Option<u8> {
+        if mb_x < self.mb_width && mb_y < self.mb_height {
+            Some(self.data[(mb_y * self.mb_width + mb_x) as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Renders the map as a translucent RGBA overlay of `width`x`height`
+    /// pixels, to be drawn over the encoded picture.
+    ///
+    /// Values are scaled so that the largest one in the map is white.
+    pub fn to_overlay(&self, width: u32, height: u32) -> Vec<u8> {
+        let max = u32::from(self.data.iter().cloned().max().unwrap_or(0));
+        let mut rgba = vec![0; width as usize * height as usize * 4];
+        for (i, pixel) in rgba.chunks_mut(4).enumerate() {
+            let x = i as u32 % width / 16;
+            let y = i as u32 / width / 16;
+            let value = u32::from(self.get(x, y).unwrap_or(0));
+            let level = (value * 255).checked_div(max).unwrap_or(0);
+            pixel.copy_from_slice(&[level as u8, level as u8, level as u8, 0x80]);
+        }
+        rgba
+    }
+}
+
+/// Same as `WebPEncode`, but also collects a macroblock map of `info_type`.
+///
+/// The map is only produced by the lossy encoder, so lossless configs are
+/// rejected.
+#[allow(non_snake_case)]
+pub fn WebPEncodeWithExtraInfo(
+    config: &WebPConfig,
+    picture: &mut WebPPicture,
+    info_type: WebPExtraInfoType,
+) -> Result<(WebpBox<[u8]>, WebPExtraInfo), WebpError> {
+    if config.lossless() {
+        return Err(WebpError::InvalidArgument(
+            "extra info requires lossy encoding".to_string(),
+        ));
+    }
+    let mb_width = (picture.width() + 15) / 16;
+    let mb_height = (picture.height() + 15) / 16;
+    let mut data = vec![0; mb_width as usize * mb_height as usize];
+    picture.0.extra_info_type = info_type as c_int;
+    picture.0.extra_info = data[..].as_mut_ptr();
+    let res = WebPEncode(config, picture);
+    picture.0.extra_info_type = 0;
+    picture.0.extra_info = ptr::null_mut();
+    let info = WebPExtraInfo {
+        info_type,
+        mb_width,
+        mb_height,
+        data,
+    };
+    res.map(|output| (output, info))
+}
+
+/// Metric for `WebPPictureDistortion` and `WebPPlaneDistortion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WebPDistortionMetric {
+    PSNR = 0,
+    SSIM = 1,
+    LSIM = 2,
+}
+
+/// Per-channel and overall distortion, in dB.
+///
+/// SSIM is reported as `-10 * log10(1 - ssim)`. Identical inputs score 99.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct WebPDistortion {
+    pub blue: f32,
+    pub green: f32,
+    pub red: f32,
+    pub alpha: f32,
+    pub all: f32,
+}
+
+/// Measures the distortion of `src` relative to `ref_`. Both pictures are compared in ARGB space.
+#[allow(non_snake_case)]
+pub fn WebPPictureDistortion(
+    src: &WebPPicture,
+    ref_: &WebPPicture,
+    metric: WebPDistortionMetric,
+) -> Result<WebPDistortion, WebpError> {
+    if (src.width(), src.height()) != (ref_.width(), ref_.height())
+        || src.width() == 0
+        || src.height() == 0
+    {
+        return Err(WebpError::InvalidArgument(format!(
+            "cannot compare pictures of {}x{} and {}x{}",
+            src.width(),
+            src.height(),
+            ref_.width(),
+            ref_.height(),
+        )));
+    }
+    src.check_pixels()?;
+    ref_.check_pixels()?;
+    let mut results: [c_float; 5] = [0.0; 5];
+    let res = unsafe {
+        sys::WebPPictureDistortion(&src.0, &ref_.0, metric as c_int, results.as_mut_ptr())
+    };
+    if res != 0 {
+        Ok(WebPDistortion {
+            blue: results[0],
+            green: results[1],
+            red: results[2],
+            alpha: results[3],
+            all: results[4],
+        })
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+#[cfg(feature = "0.6")]
+fn check_plane(
+    len: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    x_step: u32,
+) -> Result<(), WebpError> {
+    let (width, height, stride, x_step) =
+        (width as u64, height as u64, stride as u64, x_step as u64);
+    if stride < x_step * width {
+        return Err(WebpError::InvalidArgument(format!(
+            "stride {} should be greater than or equal to width {} * {}",
+            stride, width, x_step,
+        )));
+    }
+    let required = (height - 1) * stride + (width - 1) * x_step + 1;
+    if (len as u64) < required {
+        return Err(WebpError::InvalidArgument(format!(
+            "buffer length {} should be at least {}",
+            len, required,
+        )));
+    }
+    Ok(())
+}
+
+/// Measures the distortion of a single plane, returning the raw distortion and the score in dB.
+///
+/// Samples are `x_step` bytes apart; pass e.g. `&rgba[1..]` with `x_step = 4` to
+/// compare the green channel of packed RGBA buffers.
+#[cfg(feature = "0.6")]
+#[allow(non_snake_case, clippy::too_many_arguments)]
+pub fn WebPPlaneDistortion(
+    src: &[u8],
+    src_stride: u32,
+    ref_: &[u8],
+    ref_stride: u32,
+    width: u32,
+    height: u32,
+    x_step: u32,
+    metric: WebPDistortionMetric,
+) -> Result<(f32, f32), WebpError> {
+    check_dimension(width, height)?;
+    if x_step == 0 {
+        return Err(WebpError::InvalidArgument(
+            "x_step should be positive".to_string(),
+        ));
+    }
+    check_plane(src.len(), width, height, src_stride, x_step)?;
+    check_plane(ref_.len(), width, height, ref_stride, x_step)?;
+    let mut distortion: c_float = 0.0;
+    let mut result: c_float = 0.0;
+    let res = unsafe {
+        sys::WebPPlaneDistortion(
+            src.as_ptr(),
+            src_stride as usize,
+            ref_.as_ptr(),
+            ref_stride as usize,
+            width as c_int,
+            height as c_int,
+            x_step as usize,
+            metric as c_int,
+            &mut distortion,
+            &mut result,
+        )
+    };
+    if res != 0 {
+        Ok((distortion, result))
+    } else {
+        Err(WebpError::Unknown)
+    }
+}
+
+/// Parameters for `WebPEncodeTargetSSIM`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WebPQualitySearch {
+    /// The SSIM (between 0 and 1) each output must reach.
+    pub target_ssim: f32,
+    pub min_quality: f32,
+    pub max_quality: f32,
+    /// The number of bisection steps after trying `max_quality`.
+    pub max_iterations: u32,
+}
+
+impl WebPQualitySearch {
+    pub fn new(target_ssim: f32) -> Self {
+        Self {
+            target_ssim,
+            min_quality: 0.0,
+            max_quality: 100.0,
+            max_iterations: 7,
+        }
+    }
+}
+
+fn encode_and_measure(
+    config: &mut WebPConfig,
+    picture: &WebPPicture,
+    quality: f32,
+) -> Result<(WebpBox<[u8]>, f32), WebpError> {
+    config.set_quality(quality);
+    let mut candidate = WebPPictureCopy(picture)?;
+    let output = WebPEncode(config, &mut candidate)?;
+    let (width, height, rgba) = WebPDecodeRGBA(&output)?;
+    let mut decoded = WebPPictureInit()?;
+    decoded.set_use_argb(true);
+    decoded.set_dimension(width, height)?;
+    WebPPictureImportRGBA(&mut decoded, &rgba, width * 4)?;
+    let ssim = WebPPictureDistortion(&decoded, picture, WebPDistortionMetric::SSIM)?;
+    // convert back from dB
+    let ssim = 1.0 - 10_f32.powf(-ssim.all / 10.0);
+    Ok((output, ssim))
+}
+
+/// Encodes the picture at the lowest quality that meets `search.target_ssim`,
+/// bisecting over `WebPConfig::quality`.
+///
+/// Returns the smallest output that passed, with its quality and SSIM.
+/// If even `max_quality` misses the target, the output at `max_quality` is
+/// returned; compare the returned SSIM against the target to detect this.
+#[allow(non_snake_case)]
+pub fn WebPEncodeTargetSSIM(
+    config: &WebPConfig,
+    picture: &WebPPicture,
+    search: &WebPQualitySearch,
+) -> Result<(WebpBox<[u8]>, f32, f32), WebpError> {
+    if config.lossless() {
+        return Err(WebpError::InvalidArgument(
+            "quality search requires lossy encoding".to_string(),
+        ));
+    }
+    if !(0.0 <= search.min_quality
+        && search.min_quality <= search.max_quality
+        && search.max_quality <= 100.0)
+    {
+        return Err(WebpError::InvalidArgument(format!(
+            "quality range {}..{} should be within 0..100",
+            search.min_quality, search.max_quality,
+        )));
+    }
+    if !(0.0 < search.target_ssim && search.target_ssim < 1.0) {
+        return Err(WebpError::InvalidArgument(format!(
+            "target SSIM {} should be between 0 and 1",
+            search.target_ssim,
+        )));
+    }
+    let mut config = config.clone();
+
+    let (mut low, mut high) = (search.min_quality, search.max_quality);
+    let (output, ssim) = encode_and_measure(&mut config, picture, high)?;
+    let mut best = (output, high, ssim);
+    if ssim < search.target_ssim {
+        return Ok(best);
+    }
+    for _ in 0..search.max_iterations {
+        let quality = (low + high) / 2.0;
+        let (output, ssim) = encode_and_measure(&mut config, picture, quality)?;
+        if ssim >= search.target_ssim {
+            high = quality;
+            if output.len() <= best.0.len() {
+                best = (output, quality, ssim);
+            }
+        } else {
+            low = quality;
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use decode::*;
+    use test_utils::*;
+
+    fn pngs() -> &'static [Image] {
+        lazy_static! {
+            static ref PNGS: Vec<Image> = {
+                vec![
+                    decode_png(include_bytes!("../data/4.png")),
+                    decode_png(include_bytes!("../data/5.png")),
+                ]
+            };
+        }
+        &PNGS
+    }
+    fn from_webp(webp: &[u8]) -> Image {
+        let (width, height, data) = WebPDecodeRGBA(webp).unwrap();
+        Image::new(ColorType::RGBA, width, height, width * 4, data.to_vec())
+    }
+
+    #[test]
+    fn test_encode_rgb() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGB);
+            let webp = WebPEncodeRGB(
+                image.data(),
+                image.width(),
+                image.height(),
+                image.stride(),
+                75.0,
+            )
+            .unwrap();
+            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_encode_bgr() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::BGR);
+            let webp = WebPEncodeBGR(
+                image.data(),
+                image.width(),
+                image.height(),
+                image.stride(),
+                75.0,
+            )
+            .unwrap();
+            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_encode_rgba() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGBA);
+            let webp = WebPEncodeRGBA(
+                image.data(),
+                image.width(),
+                image.height(),
+                image.stride(),
+                75.0,
+            )
+            .unwrap();
+            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_encode_bgra() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::BGRA);
+            let webp = WebPEncodeBGRA(
+                image.data(),
+                image.width(),
+                image.height(),
+                image.stride(),
+                75.0,
+            )
+            .unwrap();
+            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_encode_lossless_rgb() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGB);
+            let webp =
+                WebPEncodeLosslessRGB(image.data(), image.width(), image.height(), image.stride())
+                    .unwrap();
+            assert_eq!(from_webp(&webp), image);
+        }
+    }
+
+    #[test]
+    fn test_encode_lossless_bgr() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::BGR);
+            let webp =
+                WebPEncodeLosslessBGR(image.data(), image.width(), image.height(), image.stride())
+                    .unwrap();
+            assert_eq!(from_webp(&webp), image);
+        }
+    }
+
+    #[test]
+    fn test_encode_lossless_rgba() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGBA);
+            let webp =
+                WebPEncodeLosslessRGBA(image.data(), image.width(), image.height(), image.stride())
+                    .unwrap();
+            assert_eq!(from_webp(&webp), image);
+        }
+    }
+
+    #[test]
+    fn test_encode_lossless_bgra() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::BGRA);
+            let webp =
+                WebPEncodeLosslessBGRA(image.data(), image.width(), image.height(), image.stride())
+                    .unwrap();
+            assert_eq!(from_webp(&webp), image);
+        }
+    }
+
+    #[test]
+    fn test_encode_advanced() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGBA);
+            let config = WebPConfigInit().unwrap();
+            let mut picture = WebPPictureInit().unwrap();
+            picture
+                .set_dimension(image.width(), image.height())
+                .unwrap();
+            WebPPictureImportRGBA(&mut picture, image.data(), image.stride()).unwrap();
+            let webp = WebPEncode(&config, &mut picture).unwrap();
+            assert_abs_diff_eq!(from_webp(&webp), image, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_encode_advanced_lossless() {
+        for image in pngs() {
+            let image = image.convert_auto_stride(ColorType::RGBA);
+            let mut config = WebPConfigInit().unwrap();
+            config.set_lossless(true);
+            let mut picture = WebPPictureInit().unwrap();
+            picture.set_use_argb(true);
+            picture
+                .set_dimension(image.width(), image.height())
+                .unwrap();
+            WebPPictureImportRGBA(&mut picture, image.data(), image.stride()).unwrap();
+            let webp = WebPEncode(&config, &mut picture).unwrap();
+            assert_eq!(from_webp(&webp), image);
+        }
+    }
+
+    #[test]
+    fn test_encode_advanced_errors() {
+        let config = WebPConfigInit().unwrap();
+        let mut picture = WebPPictureInit().unwrap();
+        assert_eq!(
+            WebPEncode(&config, &mut picture).err(),
+            Some(WebpError::Encode(
+                sys::WebPEncodingError::VP8_ENC_ERROR_BAD_DIMENSION
+            ))
+        );
+
+        let mut config = WebPConfigInit().unwrap();
+        config.set_quality(200.0);
+        assert!(!WebPValidateConfig(&config));
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_dimension(1, 1).unwrap();
+        WebPPictureImportRGBA(&mut picture, &[0, 0, 0, 255], 4).unwrap();
+        assert_eq!(
+            WebPEncode(&config, &mut picture).err(),
+            Some(WebpError::Encode(
+                sys::WebPEncodingError::VP8_ENC_ERROR_INVALID_CONFIGURATION
+            ))
+        );
+    }
+
+    #[test]
+    fn test_encode_invalid_arguments() {
+        fn is_invalid<T>(result: Result<T, WebpError>) -> bool {
+            match result {
+                Err(WebpError::InvalidArgument(_)) => true,
+                _ => false,
+            }
+        }
+
+        let max = sys::WEBP_MAX_DIMENSION as u32;
+        let line = vec![0; (max as usize + 1) * 4];
+        assert!(WebPEncodeRGBA(&line[..max as usize * 4], max, 1, max * 4, 75.0).is_ok());
+        assert!(is_invalid(WebPEncodeRGBA(
+            &line,
+            max + 1,
+            1,
+            (max + 1) * 4,
+            75.0
+        )));
+        assert!(is_invalid(WebPEncodeLosslessRGB(&line[..3], 1, max + 1, 3)));
+        assert!(is_invalid(WebPEncodeRGB(&[0; 6], 0, 1, 6, 75.0)));
+        assert!(is_invalid(WebPEncodeRGB(&[0; 6], 3, 1, 6, 75.0)));
+        assert!(is_invalid(WebPEncodeBGRA(&[0; 8], 1, 2, 0xFFFF_FFFF, 75.0)));
+        assert!(is_invalid(WebPEncodeLosslessBGRA(
+            &[0; 4],
+            1,
+            1,
+            0x8000_0000
+        )));
+
+        let mut picture = WebPPictureInit().unwrap();
+        assert!(is_invalid(picture.set_dimension(max + 1, 1)));
+        picture.set_dimension(2, 2).unwrap();
+        assert!(is_invalid(WebPPictureImportRGBA(&mut picture, &[0; 12], 6)));
+        assert!(is_invalid(WebPPictureImportRGB(&mut picture, &[0; 12], 4)));
+    }
+
+    fn picture_from_image(image: &Image, use_argb: bool) -> WebPPicture {
+        let image = image.convert_auto_stride(ColorType::RGBA);
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_use_argb(use_argb);
+        picture
+            .set_dimension(image.width(), image.height())
+            .unwrap();
+        WebPPictureImportRGBA(&mut picture, image.data(), image.stride()).unwrap();
+        picture
+    }
+
+    #[test]
+    fn test_picture_view() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        for image in pngs() {
+            let mut picture = picture_from_image(image, true);
+            assert!(!picture.is_view());
+            let rects = [(0, 0, 10, 7), (5, 3, 8, 8), (image.width() - 1, 0, 1, 1)];
+            for &(left, top, width, height) in &rects {
+                let mut view = picture.view(left, top, width, height).unwrap();
+                assert!(view.is_view());
+                assert_eq!((view.width(), view.height()), (width, height));
+                let webp = view.encode(&config).unwrap();
+                let expected = image.sub_image(left, top, width, height);
+                assert_eq!(from_webp(&webp), expected);
+            }
+            assert!(!picture.is_view());
+        }
+    }
+
+    #[test]
+    fn test_picture_view_yuv() {
+        let config = WebPConfigInit().unwrap();
+        for image in pngs() {
+            let mut picture = picture_from_image(image, false);
+            // left and top are snapped to even positions
+            let mut view = picture.view(3, 5, 8, 6).unwrap();
+            let webp = view.encode(&config).unwrap();
+            let expected = image.sub_image(2, 4, 8, 6);
+            assert_abs_diff_eq!(from_webp(&webp), expected, epsilon = 128);
+        }
+    }
+
+    #[test]
+    fn test_picture_view_out_of_bounds() {
+        let mut picture = picture_from_image(&pngs()[0], true);
+        let (width, height) = (picture.width(), picture.height());
+        for &(left, top, w, h) in &[
+            (0, 0, 0, 1),
+            (0, 0, width + 1, 1),
+            (1, 0, width, 1),
+            (0, height, 1, 1),
+            (0xFFFF_FFFF, 0, 2, 1),
+        ] {
+            match picture.view(left, top, w, h) {
+                Err(WebpError::InvalidArgument(_)) => {}
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+    }
+
+    fn picture_without_pixels(use_argb: bool) -> WebPPicture {
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_use_argb(use_argb);
+        picture.set_dimension(16, 16).unwrap();
+        picture
+    }
+
+    fn assert_invalid_argument<T: fmt::Debug>(res: Result<T, WebpError>) {
+        match res {
+            Err(WebpError::InvalidArgument(_)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_picture_without_pixels() {
+        for &use_argb in &[true, false] {
+            let mut picture = picture_without_pixels(use_argb);
+            assert_invalid_argument(picture.view(0, 0, 8, 8));
+            assert_invalid_argument(picture.crop(0, 0, 8, 8));
+            assert_invalid_argument(picture.rescale(8, 8));
+            assert_invalid_argument(picture.rescale_to_fit(8, 8));
+            assert!(!picture.has_transparency());
+            assert_invalid_argument(picture.blend_alpha(0x00_33_66_99));
+            assert_invalid_argument(picture.cleanup_transparent_area());
+
+            let other = picture_from_image(&pngs()[0], use_argb);
+            let mut picture = picture_without_pixels(use_argb);
+            picture
+                .set_dimension(other.width(), other.height())
+                .unwrap();
+            let psnr = WebPDistortionMetric::PSNR;
+            assert_invalid_argument(WebPPictureDistortion(&picture, &other, psnr));
+            assert_invalid_argument(WebPPictureDistortion(&other, &picture, psnr));
+        }
+    }
+
+    #[test]
+    fn test_picture_crop() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        for image in pngs() {
+            let mut picture = picture_from_image(image, true);
+            picture.crop(4, 2, 9, 11).unwrap();
+            assert!(!picture.is_view());
+            assert_eq!((picture.width(), picture.height()), (9, 11));
+            let webp = WebPEncode(&config, &mut picture).unwrap();
+            assert_eq!(from_webp(&webp), image.sub_image(4, 2, 9, 11));
+            assert!(picture.crop(0, 0, 10, 1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_picture_rescale() {
+        for image in pngs() {
+            let (width, height) = (image.width(), image.height());
+            let mut picture = picture_from_image(image, true);
+            picture.rescale(width / 2, height / 3).unwrap();
+            assert_eq!((picture.width(), picture.height()), (width / 2, height / 3));
+
+            let mut picture = picture_from_image(image, false);
+            picture.rescale(width * 2, 0).unwrap();
+            assert_eq!((picture.width(), picture.height()), (width * 2, height * 2));
+            picture.rescale(0, height).unwrap();
+            assert_eq!((picture.width(), picture.height()), (width, height));
+
+            let config = WebPConfigInit().unwrap();
+            let webp = WebPEncode(&config, &mut picture).unwrap();
+            assert_eq!(WebPGetInfo(&webp).unwrap(), (width, height));
+        }
+    }
+
+    #[test]
+    fn test_picture_rescale_invalid() {
+        let mut picture = picture_from_image(&pngs()[0], true);
+        let (width, height) = (picture.width(), picture.height());
+        for &(w, h) in &[
+            (0, 0),
+            (sys::WEBP_MAX_DIMENSION as u32 + 1, 1),
+            (0, 0xFFFF_FFFF),
+        ] {
+            match picture.rescale(w, h) {
+                Err(WebpError::InvalidArgument(_)) => {}
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+        assert_eq!((picture.width(), picture.height()), (width, height));
+        assert!(WebPPictureInit().unwrap().rescale(1, 1).is_err());
+    }
+
+    #[test]
+    fn test_picture_rescale_to_fit() {
+        let image = &pngs()[0];
+        let (width, height) = (image.width(), image.height());
+        let mut picture = picture_from_image(image, true);
+        picture.rescale_to_fit(width, height * 2).unwrap();
+        assert_eq!((picture.width(), picture.height()), (width, height));
+        picture.rescale(100, 50).unwrap();
+        picture.rescale_to_fit(40, 40).unwrap();
+        assert_eq!((picture.width(), picture.height()), (40, 20));
+        picture.rescale_to_fit(30, 5).unwrap();
+        assert_eq!((picture.width(), picture.height()), (10, 5));
+    }
+
+    #[test]
+    fn test_picture_rescale_view() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        let image = &pngs()[0];
+        let mut picture = picture_from_image(image, true);
+        {
+            let mut view = picture.view(0, 0, 8, 8).unwrap();
+            view.rescale(4, 4).unwrap();
+            assert!(!view.is_view());
+            assert_eq!((view.width(), view.height()), (4, 4));
+        }
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        assert_eq!(from_webp(&webp), *image);
+    }
+
+    #[test]
+    fn test_picture_distortion() {
+        use self::WebPDistortionMetric::*;
+
+        let config = WebPConfigInit().unwrap();
+        for image in pngs() {
+            let picture = picture_from_image(image, true);
+            let same = picture_from_image(image, true);
+            for &metric in &[PSNR, SSIM, LSIM] {
+                let d = WebPPictureDistortion(&picture, &same, metric).unwrap();
+                assert_eq!(d.all, 99.0);
+            }
+
+            let mut encoded = picture_from_image(image, false);
+            let webp = WebPEncode(&config, &mut encoded).unwrap();
+            let decoded = picture_from_image(&from_webp(&webp), true);
+            let psnr = WebPPictureDistortion(&decoded, &picture, PSNR).unwrap();
+            assert!(psnr.all > 20.0 && psnr.all < 99.0, "{:?}", psnr);
+            assert!(psnr.red < 99.0 && psnr.green < 99.0 && psnr.blue < 99.0);
+            let ssim = WebPPictureDistortion(&decoded, &picture, SSIM).unwrap();
+            assert!(ssim.all > 5.0 && ssim.all < 99.0, "{:?}", ssim);
+        }
+
+        let a = picture_from_image(&pngs()[0], true);
+        let b = picture_from_image(&pngs()[1], true);
+        assert!(WebPPictureDistortion(&a, &b, PSNR).is_err());
+    }
+
+    #[cfg(feature = "0.6")]
+    #[test]
+    fn test_plane_distortion() {
+        use self::WebPDistortionMetric::*;
+
+        let image = pngs()[0].convert_auto_stride(ColorType::RGBA);
+        let (width, height, stride) = (image.width(), image.height(), image.stride());
+        let data = image.data();
+        let (_, psnr) =
+            WebPPlaneDistortion(data, stride, data, stride, width, height, 4, PSNR).unwrap();
+        assert_eq!(psnr, 99.0);
+
+        let mut noisy = data.to_vec();
+        for (i, x) in noisy.iter_mut().enumerate() {
+            *x = x.wrapping_add((i % 7) as u8);
+        }
+        let (distortion, psnr) = WebPPlaneDistortion(
+            &noisy[1..],
+            stride,
+            &data[1..],
+            stride,
+            width,
+            height,
+            4,
+            SSIM,
+        )
+        .unwrap();
+        assert!(distortion > 0.0 && psnr < 99.0);
+
+        assert!(
+            WebPPlaneDistortion(&data[..100], stride, data, stride, width, height, 4, LSIM)
+                .is_err()
+        );
+        assert!(WebPPlaneDistortion(data, 4, data, 4, width, height, 4, LSIM).is_err());
+    }
+
+    fn picture_from_rgba(rgba: &[u8], width: u32, height: u32, use_argb: bool) -> WebPPicture {
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_use_argb(use_argb);
+        picture.set_dimension(width, height).unwrap();
+        WebPPictureImportRGBA(&mut picture, rgba, width * 4).unwrap();
+        picture
+    }
+
+    #[test]
+    fn test_picture_has_transparency() {
+        for &use_argb in &[true, false] {
+            let opaque = [255, 0, 0, 255, 0, 255, 0, 255];
+            let picture = picture_from_rgba(&opaque, 2, 1, use_argb);
+            assert!(!picture.has_transparency());
+            let translucent = [255, 0, 0, 255, 0, 255, 0, 254];
+            let picture = picture_from_rgba(&translucent, 2, 1, use_argb);
+            assert!(picture.has_transparency());
+        }
+        for image in pngs() {
+            let picture = picture_from_image(&image.to_opaque(), true);
+            assert!(!picture.has_transparency());
+        }
+    }
+
+    #[test]
+    fn test_picture_blend_alpha() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        let rgba = [
+            255, 0, 0, 255, //
+            0, 255, 0, 0, //
+            0, 0, 255, 128, //
+            255, 255, 255, 255,
+        ];
+        let mut picture = picture_from_rgba(&rgba, 4, 1, true);
+        assert!(picture.has_transparency());
+        picture.blend_alpha(0x00_33_66_99).unwrap();
+        assert!(!picture.has_transparency());
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        let expected = Image::new(
+            ColorType::RGBA,
+            4,
+            1,
+            16,
+            vec![
+                255, 0, 0, 255, //
+                0x33, 0x66, 0x99, 255, //
+                0x19, 0x33, 0xCC, 255, //
+                255, 255, 255, 255,
+            ],
+        );
+        assert_abs_diff_eq!(from_webp(&webp), expected, epsilon = 1);
+
+        let mut picture = picture_from_rgba(&rgba, 4, 1, false);
+        picture.blend_alpha(0xFF_FFFFFF).unwrap();
+        assert!(!picture.has_transparency());
+    }
+
+    #[cfg(feature = "0.5")]
+    #[test]
+    fn test_picture_cleanup_transparent_area() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        config.set_exact(true);
+        let mut rgba = vec![0; 16 * 16 * 4];
+        for (i, x) in rgba.iter_mut().enumerate() {
+            if i % 4 != 3 {
+                *x = (i * 37 % 251) as u8;
+            }
+        }
+        let mut picture = picture_from_rgba(&rgba, 16, 16, true);
+        picture.cleanup_transparent_area().unwrap();
+        assert!(picture.has_transparency());
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        let (_, _, data) = WebPDecodeRGBA(&webp).unwrap();
+        // each 8x8 block is flattened
+        for y in 0..16 {
+            for x in 0..16 {
+                let pixel = &data[(y * 16 + x) * 4..][..4];
+                let corner = &data[(y / 8 * 8 * 16 + x / 8 * 8) * 4..][..4];
+                assert_eq!(pixel, corner);
+            }
+        }
+    }
+
+    #[test]
+    fn test_picture_copy() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        for image in pngs() {
+            let mut picture = picture_from_image(image, true);
+            let mut copy = {
+                let view = picture.view(1, 2, 10, 12).unwrap();
+                WebPPictureCopy(&view).unwrap()
+            };
+            assert!(!copy.is_view());
+            let webp = WebPEncode(&config, &mut copy).unwrap();
+            assert_eq!(from_webp(&webp), image.sub_image(1, 2, 10, 12));
+        }
+        assert!(WebPPictureCopy(&WebPPictureInit().unwrap()).is_err());
+        for &use_argb in &[true, false] {
+            assert_invalid_argument(WebPPictureCopy(&picture_without_pixels(use_argb)));
+        }
+        // the ARGB pixels are not converted
+        let mut picture = picture_from_image(&pngs()[0], true);
+        picture.set_use_argb(false);
+        assert_invalid_argument(WebPPictureCopy(&picture));
+    }
+
+    #[test]
+    fn test_encode_target_ssim() {
+        let config = WebPConfigInit().unwrap();
+        let image = &pngs()[0];
+        let mut picture = picture_from_image(image, true);
+        picture.rescale_to_fit(128, 128).unwrap();
+
+        let mut search = WebPQualitySearch::new(0.85);
+        search.max_iterations = 5;
+        let (low, low_quality, low_ssim) =
+            WebPEncodeTargetSSIM(&config, &picture, &search).unwrap();
+        assert!(low_ssim >= 0.85, "{}", low_ssim);
+        assert!(low_quality < 100.0);
+
+        search.target_ssim = 0.93;
+        let (high, high_quality, high_ssim) =
+            WebPEncodeTargetSSIM(&config, &picture, &search).unwrap();
+        assert!(high_ssim >= 0.93 || high_quality == 100.0);
+        assert!(low_quality <= high_quality);
+        assert!(low.len() <= high.len());
+
+        // the source is left untouched
+        assert!(picture.use_argb());
+        assert_eq!(
+            WebPGetInfo(&low).unwrap(),
+            (picture.width(), picture.height())
+        );
+
+        search.min_quality = 80.0;
+        search.max_quality = 20.0;
+        assert!(WebPEncodeTargetSSIM(&config, &picture, &search).is_err());
+        let mut lossless = WebPConfigInit().unwrap();
+        lossless.set_lossless(true);
+        let search = WebPQualitySearch::new(0.95);
+        assert!(WebPEncodeTargetSSIM(&lossless, &picture, &search).is_err());
+    }
+
+    #[test]
+    fn test_encode_extra_info() {
+        let config = WebPConfigInit().unwrap();
+        let image = &pngs()[0];
+        let mut picture = picture_from_image(image, false);
+        let (webp, info) =
+            WebPEncodeWithExtraInfo(&config, &mut picture, WebPExtraInfoType::Segment).unwrap();
+        assert_eq!(WebPGetInfo(&webp).unwrap(), (image.width(), image.height()));
+        assert_eq!(info.info_type(), WebPExtraInfoType::Segment);
+        assert_eq!(info.mb_width(), (image.width() + 15) / 16);
+        assert_eq!(info.mb_height(), (image.height() + 15) / 16);
+        assert_eq!(
+            info.data().len(),
+            (info.mb_width() * info.mb_height()) as usize
+        );
+        assert!(info.data().iter().all(|&segment| segment < 4));
+        assert!(info.get(info.mb_width(), 0).is_none());
+
+        let (_, quant) =
+            WebPEncodeWithExtraInfo(&config, &mut picture, WebPExtraInfoType::Quant).unwrap();
+        assert!(quant.data().iter().any(|&q| q != 0));
+
+        let overlay = quant.to_overlay(image.width(), image.height());
+        assert_eq!(overlay.len(), (image.width() * image.height() * 4) as usize);
+        assert!(overlay.chunks(4).all(|pixel| pixel[3] == 0x80));
+        assert!(overlay.chunks(4).any(|pixel| pixel[0] == 0xff));
+
+        let mut lossless = WebPConfigInit().unwrap();
+        lossless.set_lossless(true);
+        assert!(
+            WebPEncodeWithExtraInfo(&lossless, &mut picture, WebPExtraInfoType::Segment).is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_alpha() {
+        let mut config = WebPConfigInit().unwrap();
+        assert_eq!(config.alpha_compression(), WebPAlphaCompression::Lossless);
+        assert_eq!(config.alpha_filtering(), WebPAlphaFiltering::Fast);
+        assert_eq!(config.alpha_quality(), 100);
+        assert!(config.set_alpha_quality(101).is_err());
+        assert_eq!(config.alpha_quality(), 100);
+
+        let image = pngs()[0].convert_auto_stride(ColorType::RGBA);
+        let mut rgba = image.data().to_vec();
+        for pixel in rgba.chunks_mut(4) {
+            // use the green channel as a natural-looking alpha plane
+            pixel[3] = pixel[1];
+        }
+        let alpha_size = |config: &WebPConfig| {
+            let mut picture = picture_from_rgba(&rgba, image.width(), image.height(), false);
+            let (webp, stats) = WebPEncodeWithStats(config, &mut picture).unwrap();
+            assert_eq!(stats.coded_size() as usize, webp.len());
+            assert!(WebPGetFeatures(&webp).unwrap().has_alpha());
+            stats.alpha_data_size()
+        };
+
+        let lossless = alpha_size(&config);
+        assert!(lossless > 0);
+        config.set_alpha_compression(WebPAlphaCompression::None);
+        let uncompressed = alpha_size(&config);
+        assert!(uncompressed > lossless);
+        assert!(uncompressed >= image.width() * image.height());
+
+        config.set_alpha_compression(WebPAlphaCompression::Lossless);
+        config.set_alpha_filtering(WebPAlphaFiltering::None);
+        assert_eq!(config.alpha_filtering(), WebPAlphaFiltering::None);
+        let unfiltered = alpha_size(&config);
+        config.set_alpha_filtering(WebPAlphaFiltering::Best);
+        assert!(alpha_size(&config) <= unfiltered);
+        config.set_alpha_filtering(WebPAlphaFiltering::Fast);
+        config.set_alpha_quality(10).unwrap();
+        assert!(alpha_size(&config) < lossless);
+    }
+
+    #[test]
+    fn test_encode_high_depth() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        #[cfg(feature = "0.5")]
+        config.set_exact(true);
+        let image = pngs()[0].convert_auto_stride(ColorType::RGBA);
+        let (width, height) = (image.width(), image.height());
+        let srgb = WebPDepthConversion::default();
+
+        let rgba16 = image
+            .data()
+            .iter()
+            .map(|&v| u16::from(v) * 257)
+            .collect::<Vec<_>>();
+        let webp = WebPEncodeRGBA16(&config, &rgba16, width, height, width * 4, &srgb).unwrap();
+        assert_eq!(from_webp(&webp), image);
+
+        // padded rows
+        let stride = width * 4 + 3;
+        let mut rgbaf = vec![0.0; (stride * height) as usize];
+        for (y, row) in image.data().chunks(width as usize * 4).enumerate() {
+            for (x, &v) in row.iter().enumerate() {
+                rgbaf[y * stride as usize + x] = f32::from(v) / 255.0;
+            }
+        }
+        let webp = WebPEncodeRGBAF32(&config, &rgbaf, width, height, stride, &srgb).unwrap();
+        assert_eq!(from_webp(&webp), image);
+
+        assert!(WebPEncodeRGBA16(&config, &rgba16, width, height, width * 3, &srgb).is_err());
+        assert!(WebPEncodeRGBAF32(&config, &rgbaf[1..], width, height, stride, &srgb).is_err());
+
+        let lossy = WebPConfigInit().unwrap();
+        let webp = WebPEncodeRGBA16(&lossy, &rgba16, width, height, width * 4, &srgb).unwrap();
+        assert_eq!(WebPGetInfo(&webp).unwrap(), (width, height));
+    }
+
+    #[test]
+    fn test_encode_high_depth_conversion() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        let linear = WebPDepthConversion {
+            transfer: WebPTransferFunction::Linear,
+            ..WebPDepthConversion::default()
+        };
+        let pixels = [0.5, 0.0, 1.0, 1.0, -1.0, 2.0, std::f32::NAN, 1.0];
+        let webp = WebPEncodeRGBAF32(&config, &pixels, 2, 1, 8, &linear).unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        assert_eq!(&rgba[..], &[188, 0, 255, 255, 0, 255, 0, 255]);
+
+        // 127.6 everywhere: rounding gives a flat 128, dithering mixes in 127
+        let flat = vec![127.6 / 255.0; 16 * 16 * 4];
+        let mut conversion = WebPDepthConversion::default();
+        let webp = WebPEncodeRGBAF32(&config, &flat, 16, 16, 16 * 4, &conversion).unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        assert!(rgba.iter().all(|&v| v == 128));
+
+        conversion.dithering = true;
+        let webp = WebPEncodeRGBAF32(&config, &flat, 16, 16, 16 * 4, &conversion).unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        assert!(rgba.iter().all(|&v| v == 127 || v == 128));
+        let mean = rgba.iter().map(|&v| f32::from(v)).sum::<f32>() / rgba.len() as f32;
+        assert!((mean - 127.6).abs() < 0.1, "{}", mean);
+    }
+
+    fn gray_image(bpp: usize, width: usize, height: usize, stride: usize) -> Vec<u8> {
+        let mut data = vec![0xaa; stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * stride + x * bpp;
+                data[i] = (x * 7 + y * 13) as u8;
+                if bpp == 2 {
+                    data[i + 1] = if x % 3 == 0 { 0xff } else { (x * y) as u8 | 1 };
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_encode_gray_lossless() {
+        let (width, height) = (37, 21);
+        let gray = gray_image(1, width, height, width + 5);
+        let webp =
+            WebPEncodeLosslessL8(&gray, width as u32, height as u32, width as u32 + 5).unwrap();
+        let (w, h, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        assert_eq!((w, h), (width as u32, height as u32));
+        for y in 0..height {
+            for x in 0..width {
+                let v = gray[y * (width + 5) + x];
+                assert_eq!(&rgba[(y * width + x) * 4..][..4], &[v, v, v, 0xff]);
+            }
+        }
+
+        let gray_alpha = gray_image(2, width, height, width * 2);
+        let webp =
+            WebPEncodeLosslessLA8(&gray_alpha, width as u32, height as u32, width as u32 * 2)
+                .unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        for (src, dst) in gray_alpha.chunks(2).zip(rgba.chunks(4)) {
+            // fully transparent pixels may lose their colour
+            if src[1] != 0 {
+                assert_eq!(dst, &[src[0], src[0], src[0], src[1]]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_gray_lossy() {
+        let (width, height) = (37u32, 21u32);
+        let gray = gray_image(1, width as usize, height as usize, width as usize);
+        let webp = WebPEncodeL8(&gray, width, height, width, 90.0).unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        for (&src, dst) in gray.iter().zip(rgba.chunks(4)) {
+            assert!((i32::from(dst[1]) - i32::from(src)).abs() < 24);
+            // neutral chroma stays close to gray
+            assert!((i32::from(dst[0]) - i32::from(dst[2])).abs() < 8);
+            assert_eq!(dst[3], 0xff);
+        }
+
+        let gray_alpha = gray_image(2, width as usize, height as usize, width as usize * 2);
+        let webp = WebPEncodeLA8(&gray_alpha, width, height, width * 2, 90.0).unwrap();
+        assert!(WebPGetFeatures(&webp).unwrap().has_alpha());
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        for (src, dst) in gray_alpha.chunks(2).zip(rgba.chunks(4)) {
+            assert_eq!(dst[3], src[1]);
+        }
+
+        // a lossy picture gets the luma plane filled directly
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_dimension(2, 1).unwrap();
+        WebPPictureImportL8(&mut picture, &[0, 255], 2).unwrap();
+        let luma = unsafe { slice::from_raw_parts(picture.0.y, 2) };
+        assert_eq!(luma, &[16, 235]);
+    }
+
+    #[test]
+    fn test_encode_gray_invalid() {
+        let gray = [0; 16];
+        assert!(WebPEncodeL8(&gray, 4, 4, 3, 75.0).is_err());
+        assert!(WebPEncodeL8(&gray, 4, 5, 4, 75.0).is_err());
+        assert!(WebPEncodeLA8(&gray, 4, 4, 4, 75.0).is_err());
+        assert!(WebPEncodeLosslessLA8(&gray, 2, 4, 4).is_ok());
+        assert!(WebPEncodeLosslessL8(&gray, 0, 4, 4).is_err());
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_dimension(4, 4).unwrap();
+        assert!(WebPPictureImportLA8(&mut picture, &gray, 4).is_err());
+    }
+
+    #[test]
+    fn test_picture_import_premultiplied() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        #[cfg(feature = "0.5")]
+        config.set_exact(true);
+
+        // opaque pixels survive unchanged
+        let image = pngs()[0].to_opaque().convert_auto_stride(ColorType::RGBA);
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_use_argb(true);
+        picture
+            .set_dimension(image.width(), image.height())
+            .unwrap();
+        WebPPictureImportPremultipliedRGBA(&mut picture, image.data(), image.stride()).unwrap();
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        assert_eq!(from_webp(&webp), image);
+
+        let premultiplied = [
+            255, 128, 0, 255, // opaque
+            64, 32, 0, 128, // half transparent
+            3, 1, 0, 3, // nearly transparent
+            9, 200, 1, 2, // invalid: colour above alpha
+            10, 20, 30, 0, // transparent
+        ];
+        let mut picture = WebPPictureInit().unwrap();
+        picture.set_use_argb(true);
+        picture.set_dimension(5, 1).unwrap();
+        WebPPictureImportPremultipliedRGBA(&mut picture, &premultiplied, 20).unwrap();
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        let (_, _, rgba) = WebPDecodeRGBA(&webp).unwrap();
+        assert_eq!(
+            &rgba[..],
+            &[
+                255, 128, 0, 255, //
+                128, 64, 0, 128, //
+                255, 85, 0, 3, //
+                255, 255, 128, 2, //
+                0, 0, 0, 0,
+            ][..]
+        );
+
+        let mut bgra = premultiplied;
+        for pixel in bgra.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+        WebPPictureImportPremultipliedBGRA(&mut picture, &bgra, 20).unwrap();
+        let webp = WebPEncode(&config, &mut picture).unwrap();
+        assert_eq!(WebPDecodeRGBA(&webp).unwrap().2, rgba);
+
+        assert!(WebPPictureImportPremultipliedRGBA(&mut picture, &premultiplied, 16).is_err());
+        assert_invalid_argument(WebPPictureImportPremultipliedRGBA(
+            &mut WebPPictureInit().unwrap(),
+            &[],
+            0,
+        ));
+    }
+
+    #[cfg(feature = "0.5")]
+    #[test]
+    fn test_encoder() {
+        let mut config = WebPConfigInit().unwrap();
+        config.set_lossless(true);
+        config.set_exact(true);
+        let mut encoder = Encoder::new(config).unwrap();
+        for _ in 0..2 {
+            for image in pngs() {
+                let image = image.convert_auto_stride(ColorType::RGBA);
+                let webp = encoder
+                    .encode_rgba(image.data(), image.width(), image.height(), image.stride())
+                    .unwrap();
+                assert_eq!(from_webp(webp), image);
+                let sub = image.sub_image(3, 5, 17, 9);
+                let webp = encoder
+                    .encode_rgba(sub.data(), sub.width(), sub.height(), sub.stride())
+                    .unwrap();
+                assert_eq!(from_webp(webp), sub);
+            }
+            encoder.reset();
+        }
+
+        let mut invalid = encoder.config().clone();
+        invalid.set_quality(101.0);
+        assert!(encoder.set_config(invalid).is_err());
+        assert!(encoder.config().lossless());
+        let mut lossy_config = encoder.config().clone();
+        lossy_config.set_lossless(false);
+        encoder.set_config(lossy_config).unwrap();
+        let image = pngs()[0].convert_auto_stride(ColorType::RGB);
+        let lossy = encoder
+            .encode_rgb(image.data(), image.width(), image.height(), image.stride())
+            .unwrap()
+            .to_vec();
+        let webp = WebPEncodeRGB(
+            image.data(),
+            image.width(),
+            image.height(),
+            image.stride(),
+            encoder.config().quality(),
+        )
+        .unwrap();
+        assert_eq!(&lossy[..], &webp[..]);
+
+        assert!(encoder.encode_rgb(image.data(), 0, 1, 0).is_err());
+        assert!(encoder
+            .encode_rgb(image.data(), image.width(), image.height(), 1)
+            .is_err());
+
+        let mut invalid = WebPConfigInit().unwrap();
+        invalid.set_quality(101.0);
+        assert!(Encoder::new(invalid).is_err());
+    }
+
+    #[cfg(feature = "0.5")]
+    #[test]
+    fn test_config_lossless_preset() {
+        let mut config = WebPConfigInit().unwrap();
+        WebPConfigLosslessPreset(&mut config, 0).unwrap();
+        assert!(config.lossless());
+        assert_eq!((config.method(), config.quality()), (0, 0.0));
+        WebPConfigLosslessPreset(&mut config, 9).unwrap();
+        assert_eq!((config.method(), config.quality()), (6, 100.0));
+        assert!(WebPValidateConfig(&config));
+        assert!(WebPConfigLosslessPreset(&mut config, 10).is_err());
+        assert_eq!((config.method(), config.quality()), (6, 100.0));
+    }
+
+    #[cfg(feature = "0.5")]
+    #[test]
+    fn test_encode_lossless_with_options() {
+        let image = pngs()[0]
+            .sub_image(0, 0, 128, 128)
+            .convert_auto_stride(ColorType::RGBA);
+        let (width, height, stride) = (image.width(), image.height(), image.stride());
+        let mut sizes = Vec::new();
+        for &level in &[0, 9] {
+            let options = WebPLosslessOptions {
+                level,
+                ..WebPLosslessOptions::default()
+            };
+            let (webp, stats) =
+                WebPEncodeLosslessRGBAWithOptions(image.data(), width, height, stride, &options)
+                    .unwrap();
+            assert_eq!(from_webp(&webp).to_opaque(), image.to_opaque());
+            assert_eq!(stats.coded_size() as usize, webp.len());
+            assert!(!stats.uses_palette());
+            sizes.push(webp.len());
+        }
+        assert!(sizes[1] < sizes[0], "{:?}", sizes);
+
+        // few colours: a palette is used
+        let mut rgba = vec![0; 64 * 64 * 4];
+        for (i, pixel) in rgba.chunks_mut(4).enumerate() {
+            let colors = [[255, 0, 0, 255], [0, 0, 255, 255], [0, 128, 0, 255]];
+            pixel.copy_from_slice(&colors[i % 64 / 8 % 3]);
+        }
+        #[allow(unused_mut)]
+        let mut options = WebPLosslessOptions::default();
+        #[cfg(feature = "0.6")]
+        {
+            options.use_delta_palette = true;
+        }
+        let (webp, stats) =
+            WebPEncodeLosslessRGBAWithOptions(&rgba, 64, 64, 64 * 4, &options).unwrap();
+        assert_eq!(WebPDecodeRGBA(&webp).unwrap().2.to_vec(), rgba);
+        assert!(stats.uses_palette());
+        assert_eq!(stats.palette_size(), 3);
+        assert!(stats.cache_bits() <= 11);
+
+        let options = WebPLosslessOptions {
+            level: 10,
+            ..WebPLosslessOptions::default()
+        };
+        assert!(WebPEncodeLosslessRGBAWithOptions(&rgba, 64, 64, 64 * 4, &options).is_err());
     }
 }
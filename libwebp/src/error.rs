@@ -1,17 +1,162 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct WebpUnknownError;
+use libwebp_sys as sys;
 
-impl fmt::Display for WebpUnknownError {
+use libwebp_sys::{VP8StatusCode, WebPDemuxState, WebPEncodingError, WebPMuxError};
+
+/// Errors from the libwebp wrappers.
+///
+/// Variants originating from libwebp keep the original status code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebpError {
+    /// The decoder failed with the status code.
+    Decode(VP8StatusCode),
+    /// The encoder failed with the error code stored in `WebPPicture::error_code`.
+    Encode(WebPEncodingError),
+    /// A `WebPMux` function failed with the error code.
+    Mux(WebPMuxError),
+    /// The demuxer could not parse the data. Holds the state it stopped at.
+    DemuxParse(WebPDemuxState),
+    /// An argument was rejected before calling into libwebp.
+    InvalidArgument(String),
+    /// libwebp reported a failure without any further information.
+    Unknown,
+    // Keeps matches non-exhaustive so that variants can be added later.
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl WebpError {
+    pub(crate) fn from_encoding_error(code: WebPEncodingError) -> Self {
+        match code {
+            WebPEncodingError::VP8_ENC_OK | WebPEncodingError::VP8_ENC_ERROR_LAST => {
+                WebpError::Unknown
+            }
+            code => WebpError::Encode(code),
+        }
+    }
+}
+
+fn decode_message(code: VP8StatusCode) -> &'static str {
+    use self::sys::VP8StatusCode::*;
+
+    match code {
+        VP8_STATUS_OK => "no error",
+        VP8_STATUS_OUT_OF_MEMORY => "out of memory",
+        VP8_STATUS_INVALID_PARAM => "invalid parameter",
+        VP8_STATUS_BITSTREAM_ERROR => "bitstream error",
+        VP8_STATUS_UNSUPPORTED_FEATURE => "unsupported feature",
+        VP8_STATUS_SUSPENDED => "decoding suspended",
+        VP8_STATUS_USER_ABORT => "abort request by user",
+        VP8_STATUS_NOT_ENOUGH_DATA => "not enough data",
+    }
+}
+
+fn encode_message(code: WebPEncodingError) -> &'static str {
+    use self::sys::WebPEncodingError::*;
+
+    match code {
+        VP8_ENC_OK => "no error",
+        VP8_ENC_ERROR_OUT_OF_MEMORY => "memory error allocating objects",
+        VP8_ENC_ERROR_BITSTREAM_OUT_OF_MEMORY => "memory error while flushing bits",
+        VP8_ENC_ERROR_NULL_PARAMETER => "a pointer parameter is NULL",
+        VP8_ENC_ERROR_INVALID_CONFIGURATION => "configuration is invalid",
+        VP8_ENC_ERROR_BAD_DIMENSION => "picture has invalid width/height",
+        VP8_ENC_ERROR_PARTITION0_OVERFLOW => "partition is bigger than 512k",
+        VP8_ENC_ERROR_PARTITION_OVERFLOW => "partition is bigger than 16M",
+        VP8_ENC_ERROR_BAD_WRITE => "error while flushing bytes",
+        VP8_ENC_ERROR_FILE_TOO_BIG => "file is bigger than 4G",
+        VP8_ENC_ERROR_USER_ABORT => "abort request by user",
+        VP8_ENC_ERROR_LAST => "unknown error",
+    }
+}
+
+fn mux_message(code: WebPMuxError) -> &'static str {
+    use self::sys::WebPMuxError::*;
+
+    match code {
+        WEBP_MUX_OK => "no error",
+        WEBP_MUX_NOT_FOUND => "not found",
+        WEBP_MUX_INVALID_ARGUMENT => "invalid argument",
+        WEBP_MUX_BAD_DATA => "bad data",
+        WEBP_MUX_MEMORY_ERROR => "memory error",
+        WEBP_MUX_NOT_ENOUGH_DATA => "not enough data",
+    }
+}
+
+fn demux_message(state: WebPDemuxState) -> &'static str {
+    use self::sys::WebPDemuxState::*;
+
+    match state {
+        WEBP_DEMUX_PARSE_ERROR => "parse error",
+        WEBP_DEMUX_PARSING_HEADER => "incomplete header",
+        WEBP_DEMUX_PARSED_HEADER => "incomplete data",
+        WEBP_DEMUX_DONE => "no error",
+    }
+}
+
+impl fmt::Display for WebpError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.description().fmt(f)
+        match *self {
+            WebpError::Decode(code) => write!(f, "webp decode error: {}", decode_message(code)),
+            WebpError::Encode(code) => write!(f, "webp encode error: {}", encode_message(code)),
+            WebpError::Mux(code) => write!(f, "webp mux error: {}", mux_message(code)),
+            WebpError::DemuxParse(state) => {
+                write!(f, "webp demux error: {}", demux_message(state))
+            }
+            WebpError::InvalidArgument(ref msg) => write!(f, "invalid argument: {}", msg),
+            WebpError::Unknown | WebpError::__Nonexhaustive => f.write_str("webp library error"),
+        }
     }
 }
 
-impl Error for WebpUnknownError {
-    fn description(&self) -> &str {
-        "webp library error"
+impl Error for WebpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_encoding_error() {
+        use self::sys::WebPEncodingError::*;
+
+        assert_eq!(
+            WebpError::from_encoding_error(VP8_ENC_ERROR_BAD_DIMENSION),
+            WebpError::Encode(VP8_ENC_ERROR_BAD_DIMENSION)
+        );
+        assert_eq!(
+            WebpError::from_encoding_error(VP8_ENC_OK),
+            WebpError::Unknown
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            WebpError::Encode(WebPEncodingError::VP8_ENC_ERROR_BAD_DIMENSION).to_string(),
+            "webp encode error: picture has invalid width/height"
+        );
+        assert_eq!(
+            WebpError::Decode(VP8StatusCode::VP8_STATUS_BITSTREAM_ERROR).to_string(),
+            "webp decode error: bitstream error"
+        );
+        assert_eq!(
+            WebpError::Mux(WebPMuxError::WEBP_MUX_BAD_DATA).to_string(),
+            "webp mux error: bad data"
+        );
+        assert_eq!(
+            WebpError::DemuxParse(WebPDemuxState::WEBP_DEMUX_PARSE_ERROR).to_string(),
+            "webp demux error: parse error"
+        );
+    }
+
+    #[test]
+    fn test_question_mark() {
+        fn f() -> Result<(), Box<dyn Error>> {
+            Err(WebpError::Unknown)?;
+            Ok(())
+        }
+        assert_eq!(f().unwrap_err().to_string(), "webp library error");
     }
 }
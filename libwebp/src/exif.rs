@@ -0,0 +1,288 @@
+use error::WebpError;
+
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+/// The EXIF orientation tag: how the stored image must be transformed to
+/// be displayed upright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExifOrientation {
+    Normal = 1,
+    FlipHorizontal = 2,
+    Rotate180 = 3,
+    FlipVertical = 4,
+    Transpose = 5,
+    /// Rotate 90 degrees clockwise.
+    Rotate90 = 6,
+    Transverse = 7,
+    /// Rotate 270 degrees clockwise.
+    Rotate270 = 8,
+}
+
+impl ExifOrientation {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        use self::ExifOrientation::*;
+
+        Some(match value {
+            1 => Normal,
+            2 => FlipHorizontal,
+            3 => Rotate180,
+            4 => FlipVertical,
+            5 => Transpose,
+            6 => Rotate90,
+            7 => Transverse,
+            8 => Rotate270,
+            _ => return None,
+        })
+    }
+
+    /// Whether displaying the image swaps its width and height.
+    pub fn swaps_dimensions(self) -> bool {
+        self as u16 >= 5
+    }
+}
+
+/// Tags read from an EXIF block.
+///
+/// Only IFD0 and the date of the Exif sub-IFD are looked at. Tags with an
+/// unexpected type or value are treated as missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExifInfo {
+    pub orientation: Option<ExifOrientation>,
+    /// `DateTime`, formatted as `YYYY:MM:DD HH:MM:SS`.
+    pub date_time: Option<String>,
+    /// `DateTimeOriginal`, formatted as `YYYY:MM:DD HH:MM:SS`.
+    pub date_time_original: Option<String>,
+}
+
+impl ExifInfo {
+    /// Parses a TIFF-structured EXIF block, as stored in the `EXIF` chunk.
+    ///
+    /// A leading `Exif\0\0` marker, as written by some tools, is skipped.
+    pub fn parse(data: &[u8]) -> Result<Self, WebpError> {
+        let data = if data.starts_with(b"Exif\0\0") {
+            &data[6..]
+        } else {
+            data
+        };
+        let tiff = Tiff::new(data)?;
+        let ifd0 = tiff
+            .u32(4)
+            .ok_or_else(|| malformed("truncated TIFF header"))?;
+        let mut info = ExifInfo::default();
+        let mut exif_ifd = None;
+        for entry in tiff.entries(ifd0)? {
+            match entry.tag {
+                TAG_ORIENTATION => {
+                    info.orientation = tiff.short(&entry).and_then(ExifOrientation::from_u16);
+                }
+                TAG_DATE_TIME => info.date_time = tiff.ascii(&entry),
+                TAG_EXIF_IFD => exif_ifd = tiff.long(&entry),
+                _ => {}
+            }
+        }
+        if let Some(offset) = exif_ifd {
+            // a broken sub-IFD does not invalidate what IFD0 gave us
+            if let Ok(entries) = tiff.entries(offset) {
+                for entry in entries {
+                    if entry.tag == TAG_DATE_TIME_ORIGINAL {
+                        info.date_time_original = tiff.ascii(&entry);
+                    }
+                }
+            }
+        }
+        Ok(info)
+    }
+}
+
+fn malformed(reason: &str) -> WebpError {
+    WebpError::InvalidArgument(format!("malformed EXIF: {}", reason))
+}
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    // offset of the 4-byte value field
+    value_offset: usize,
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, WebpError> {
+        let big_endian = match data.get(..4) {
+            Some(b"II*\0") => false,
+            Some(b"MM\0*") => true,
+            _ => return Err(malformed("missing TIFF header")),
+        };
+        Ok(Tiff { data, big_endian })
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        self.data.get(offset..offset.checked_add(len)?)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let b = self.bytes(offset, 2)?;
+        Some(if self.big_endian {
+            u16::from(b[0]) << 8 | u16::from(b[1])
+        } else {
+            u16::from(b[1]) << 8 | u16::from(b[0])
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<usize> {
+        let (a, b) = (self.u16(offset)?, self.u16(offset.checked_add(2)?)?);
+        let (high, low) = if self.big_endian { (a, b) } else { (b, a) };
+        Some((u32::from(high) << 16 | u32::from(low)) as usize)
+    }
+
+    fn entries(&self, offset: usize) -> Result<Vec<Entry>, WebpError> {
+        let count = self
+            .u16(offset)
+            .ok_or_else(|| malformed("IFD out of bounds"))?;
+        (0..usize::from(count))
+            .map(|i| {
+                // offsets come from the data; never let them overflow
+                let start = offset.saturating_add(2 + i * 12);
+                match (
+                    self.u16(start),
+                    self.u16(start.saturating_add(2)),
+                    self.u32(start.saturating_add(4)),
+                ) {
+                    (Some(tag), Some(kind), Some(count))
+                        if self.bytes(start.saturating_add(8), 4).is_some() =>
+                    {
+                        Ok(Entry {
+                            tag,
+                            kind,
+                            count: count as u32,
+                            value_offset: start.saturating_add(8),
+                        })
+                    }
+                    _ => Err(malformed("IFD entry out of bounds")),
+                }
+            })
+            .collect()
+    }
+
+    fn short(&self, entry: &Entry) -> Option<u16> {
+        if entry.kind != TYPE_SHORT || entry.count == 0 {
+            return None;
+        }
+        self.u16(entry.value_offset)
+    }
+
+    fn long(&self, entry: &Entry) -> Option<usize> {
+        if entry.kind != TYPE_LONG || entry.count == 0 {
+            return None;
+        }
+        self.u32(entry.value_offset)
+    }
+
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        if entry.kind != TYPE_ASCII {
+            return None;
+        }
+        let len = entry.count as usize;
+        let offset = if len <= 4 {
+            entry.value_offset
+        } else {
+            self.u32(entry.value_offset)?
+        };
+        let bytes = self.bytes(offset, len)?;
+        let bytes = match bytes.iter().position(|&b| b == 0) {
+            Some(end) => &bytes[..end],
+            None => bytes,
+        };
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Little-endian TIFF with orientation, DateTime and an Exif sub-IFD.
+    fn sample_exif(orientation: u16) -> Vec<u8> {
+        let mut data = b"II*\0\x08\0\0\0".to_vec();
+        // IFD0 at 8: 3 entries, next IFD offset
+        data.extend_from_slice(&[3, 0]);
+        data.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+        data.extend_from_slice(&[orientation as u8, (orientation >> 8) as u8, 0, 0]);
+        data.extend_from_slice(&[0x32, 0x01, 2, 0, 20, 0, 0, 0, 50, 0, 0, 0]);
+        data.extend_from_slice(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 70, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(data.len(), 50);
+        data.extend_from_slice(b"2019:01:02 03:04:05\0");
+        // Exif IFD at 70
+        data.extend_from_slice(&[1, 0]);
+        data.extend_from_slice(&[0x03, 0x90, 2, 0, 20, 0, 0, 0, 88, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(data.len(), 88);
+        data.extend_from_slice(b"2018:12:31 23:59:59\0");
+        data
+    }
+
+    #[test]
+    fn test_parse_exif() {
+        let info = ExifInfo::parse(&sample_exif(6)).unwrap();
+        assert_eq!(info.orientation, Some(ExifOrientation::Rotate90));
+        assert!(info.orientation.unwrap().swaps_dimensions());
+        assert_eq!(info.date_time.as_ref().unwrap(), "2019:01:02 03:04:05");
+        assert_eq!(
+            info.date_time_original.as_ref().unwrap(),
+            "2018:12:31 23:59:59"
+        );
+
+        let mut prefixed = b"Exif\0\0".to_vec();
+        prefixed.extend(sample_exif(1));
+        let info = ExifInfo::parse(&prefixed).unwrap();
+        assert_eq!(info.orientation, Some(ExifOrientation::Normal));
+
+        // invalid orientation values are ignored
+        let info = ExifInfo::parse(&sample_exif(9)).unwrap();
+        assert_eq!(info.orientation, None);
+        assert!(info.date_time.is_some());
+    }
+
+    #[test]
+    fn test_parse_exif_big_endian() {
+        let mut data = b"MM\0*\0\0\0\x08\0\x01".to_vec();
+        data.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 3, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        let info = ExifInfo::parse(&data).unwrap();
+        assert_eq!(info.orientation, Some(ExifOrientation::Rotate180));
+        assert_eq!(info.date_time, None);
+    }
+
+    #[test]
+    fn test_parse_exif_malformed() {
+        assert!(ExifInfo::parse(b"").is_err());
+        assert!(ExifInfo::parse(b"JFIF").is_err());
+        assert!(ExifInfo::parse(b"II*\0\xff\xff\xff\xff").is_err());
+
+        // every truncation and corrupted byte must be handled gracefully
+        let data = sample_exif(3);
+        for len in 0..data.len() {
+            let _ = ExifInfo::parse(&data[..len]);
+        }
+        for i in 0..data.len() {
+            for &value in &[0x00, 0x7f, 0xff] {
+                let mut corrupted = data.clone();
+                corrupted[i] = value;
+                let _ = ExifInfo::parse(&corrupted);
+            }
+        }
+    }
+}
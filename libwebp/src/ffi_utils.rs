@@ -2,33 +2,55 @@ use std::error::Error;
 use std::fmt;
 use std::os::raw::c_int;
 
+use libwebp_sys::WEBP_MAX_DIMENSION;
+
+use error::WebpError;
+
 #[cfg(any(target_pointer_width = "32", target_pointer_width = "64"))]
-pub(crate) fn check_stride(len: usize, width: u32, height: u32, stride: u32, bpp: u32) {
-    assert_eq!(
-        Some(len),
-        (stride as usize).checked_mul(height as usize),
-        "buffer length {} should be equal to stride {} * height {}",
-        len,
-        stride,
-        height,
-    );
-    assert!(
-        stride / bpp >= width,
-        "stride {} should be greater than or equal to width {} * {}",
-        stride,
-        width,
-        bpp,
-    );
+pub(crate) fn check_stride(
+    len: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    bpp: u32,
+) -> Result<(), WebpError> {
+    if Some(len) != (stride as usize).checked_mul(height as usize) {
+        return Err(WebpError::InvalidArgument(format!(
+            "buffer length {} should be equal to stride {} * height {}",
+            len, stride, height,
+        )));
+    }
+    if stride / bpp < width {
+        return Err(WebpError::InvalidArgument(format!(
+            "stride {} should be greater than or equal to width {} * {}",
+            stride, width, bpp,
+        )));
+    }
+    Ok(())
 }
 
-pub(crate) fn check_int(value: u32, name: &str) -> c_int {
+pub(crate) fn check_int(value: u32, name: &str) -> Result<c_int, WebpError> {
     if let Ok(value) = PolyfillTryInto::try_into(value) {
-        value
+        Ok(value)
     } else {
-        panic!("{} {} out of range for c_int", name, value);
+        Err(WebpError::InvalidArgument(format!(
+            "{} {} out of range for c_int",
+            name, value,
+        )))
     }
 }
 
+pub(crate) fn check_dimension(width: u32, height: u32) -> Result<(), WebpError> {
+    let max = WEBP_MAX_DIMENSION as u32;
+    if width == 0 || height == 0 || width > max || height > max {
+        return Err(WebpError::InvalidArgument(format!(
+            "dimension {}x{} should be between 1x1 and {}x{}",
+            width, height, max, max,
+        )));
+    }
+    Ok(())
+}
+
 pub(crate) trait PolyfillTryFrom<T>: Sized {
     type Error;
     fn try_from(value: T) -> Result<Self, Self::Error>;
@@ -55,7 +77,7 @@ where
 impl PolyfillTryFrom<i64> for u32 {
     type Error = TryFromIntError;
     fn try_from(value: i64) -> Result<Self, Self::Error> {
-        if value >= 0 && value < 0x1_0000_0000 {
+        if (0..0x1_0000_0000).contains(&value) {
             Ok(value as u32)
         } else {
             Err(TryFromIntError(()))
@@ -119,58 +141,67 @@ pub struct TryFromIntError(());
 
 impl fmt::Display for TryFromIntError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        self.description().fmt(fmt)
+        fmt.write_str("out of range integral type conversion attempted")
     }
 }
 
-impl Error for TryFromIntError {
-    fn description(&self) -> &str {
-        "out of range integral type conversion attempted"
-    }
-}
+impl Error for TryFromIntError {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn message<T: fmt::Debug>(result: Result<T, WebpError>) -> String {
+        match result.unwrap_err() {
+            WebpError::InvalidArgument(msg) => msg,
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn test_check_stride() {
-        check_stride(1056, 15, 22, 48, 3)
+        check_stride(1056, 15, 22, 48, 3).unwrap();
     }
 
     #[test]
-    #[should_panic(expected = "buffer length")]
     fn test_check_stride_less_length() {
-        check_stride(1055, 15, 22, 48, 3)
+        assert!(message(check_stride(1055, 15, 22, 48, 3)).starts_with("buffer length"));
     }
 
     #[test]
-    #[should_panic(expected = "buffer length")]
     fn test_check_stride_greater_length() {
-        check_stride(1057, 15, 22, 48, 3)
+        assert!(message(check_stride(1057, 15, 22, 48, 3)).starts_with("buffer length"));
     }
 
     #[test]
-    #[should_panic(expected = "buffer length")]
     fn test_check_stride_overflow() {
-        check_stride(1, 2000000, 641, 6700417, 3)
+        assert!(message(check_stride(1, 2000000, 641, 6700417, 3)).starts_with("buffer length"));
     }
 
     #[test]
-    #[should_panic(expected = "stride")]
     fn test_check_stride_greater_stride() {
-        check_stride(1056, 15, 22, 48, 4)
+        assert!(message(check_stride(1056, 15, 22, 48, 4)).starts_with("stride"));
     }
 
     #[test]
     fn test_check_int() {
-        assert_eq!(check_int(42, "foo"), 42);
-        assert_eq!(check_int(0, "foo"), 0);
+        assert_eq!(check_int(42, "foo").unwrap(), 42);
+        assert_eq!(check_int(0, "foo").unwrap(), 0);
+        assert_eq!(check_int(0x7FFF_FFFF, "foo").unwrap(), 0x7FFF_FFFF);
     }
 
     #[test]
-    #[should_panic(expected = "foo")]
     fn test_check_int_overflow() {
-        check_int(0xFFFF_FFFF, "foo");
+        assert!(message(check_int(0xFFFF_FFFF, "foo")).starts_with("foo"));
+        assert!(message(check_int(0x8000_0000, "foo")).starts_with("foo"));
+    }
+
+    #[test]
+    fn test_check_dimension() {
+        check_dimension(1, 1).unwrap();
+        check_dimension(16383, 16383).unwrap();
+        assert!(message(check_dimension(16384, 1)).starts_with("dimension"));
+        assert!(message(check_dimension(1, 16384)).starts_with("dimension"));
+        assert!(message(check_dimension(0, 1)).starts_with("dimension"));
     }
 }
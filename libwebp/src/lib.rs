@@ -38,13 +38,19 @@ extern crate lazy_static;
 
 pub use boxed::*;
 pub use decode::*;
+pub use demux::*;
 pub use encode::*;
 pub use error::*;
+pub use exif::*;
+pub use mux::*;
 
 mod boxed;
 mod decode;
+mod demux;
 mod encode;
 mod error;
+mod exif;
 mod ffi_utils;
+mod mux;
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,1431 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Sub};
+use std::os::raw::*;
+use std::slice;
+
+use libwebp_sys as sys;
+use libwebp_sys::WebPMuxError;
+
+pub use libwebp_sys::{WebPMuxAnimBlend, WebPMuxAnimDispose};
+
+use boxed::WebpBox;
+use decode::WebPGetInfo;
+use error::WebpError;
+use exif::ExifInfo;
+
+#[allow(non_snake_case)]
+pub fn WebPGetMuxVersion() -> u32 {
+    (unsafe { sys::WebPGetMuxVersion() }) as u32
+}
+
+pub(crate) fn mux_result(err: WebPMuxError) -> Result<(), WebpError> {
+    match err {
+        WebPMuxError::WEBP_MUX_OK => Ok(()),
+        err => Err(WebpError::Mux(err)),
+    }
+}
+
+pub(crate) fn webp_data(data: &[u8]) -> sys::WebPData {
+    sys::WebPData {
+        bytes: data.as_ptr(),
+        size: data.len(),
+    }
+}
+
+/// A chunk identifier that is free for application use.
+///
+/// IDs must be four printable ASCII characters and must not name a chunk
+/// defined by the WebP container format.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FourCC([u8; 4]);
+
+impl FourCC {
+    /// Chunk IDs defined by the container format, which have dedicated APIs.
+    pub const RESERVED: [&'static [u8; 4]; 12] = [
+        b"RIFF", b"WEBP", b"VP8 ", b"VP8L", b"VP8X", b"ALPH", b"ANIM", b"ANMF", b"FRGM", b"ICCP",
+        b"EXIF", b"XMP ",
+    ];
+
+    pub fn new(id: &[u8; 4]) -> Result<Self, WebpError> {
+        if !id.iter().all(|&b| (0x20..0x7f).contains(&b)) {
+            return Err(WebpError::InvalidArgument(format!(
+                "chunk ID {:?} should be printable ASCII",
+                id,
+            )));
+        }
+        if FourCC::RESERVED.contains(&id) {
+            return Err(WebpError::InvalidArgument(format!(
+                "chunk ID {:?} is reserved",
+                String::from_utf8_lossy(id),
+            )));
+        }
+        Ok(FourCC(*id))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FourCC({:?})", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl fmt::Display for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+// The chunk IDs libwebp has a dedicated slot for (`kChunks` in
+// muxinternal.c); it stores all other chunks as `WEBP_CHUNK_UNKNOWN`.
+#[cfg(feature = "0.6")]
+const KNOWN_CHUNK_IDS: [&[u8; 4]; 9] = [
+    b"VP8X", b"ICCP", b"ANIM", b"ANMF", b"ALPH", b"VP8 ", b"VP8L", b"EXIF", b"XMP ",
+];
+#[cfg(not(feature = "0.6"))]
+const KNOWN_CHUNK_IDS: [&[u8; 4]; 10] = [
+    b"VP8X", b"ICCP", b"ANIM", b"ANMF", b"FRGM", b"ALPH", b"VP8 ", b"VP8L", b"EXIF", b"XMP ",
+];
+
+// Lists the IDs of the top-level chunks of a RIFF container, stopping at
+// the first truncated chunk.
+pub(crate) fn riff_chunk_ids(data: &[u8]) -> Vec<[u8; 4]> {
+    let mut ids = Vec::new();
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return ids;
+    }
+    let mut offset = 12;
+    while let Some(header) = data.get(offset..offset + 8) {
+        let id = [header[0], header[1], header[2], header[3]];
+        let size = u32::from(header[4])
+            | u32::from(header[5]) << 8
+            | u32::from(header[6]) << 16
+            | u32::from(header[7]) << 24;
+        ids.push(id);
+        let padded = (size as usize).saturating_add(size as usize & 1);
+        offset = match (offset + 8).checked_add(padded) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    ids
+}
+
+/// Feature flags of a WebP container, as stored in its `VP8X` chunk.
+///
+/// Obtained from `Mux::features` or `Demuxer::features`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ContainerFeatures {
+    bits: u32,
+}
+
+impl ContainerFeatures {
+    pub const ANIMATION: ContainerFeatures = ContainerFeatures {
+        bits: sys::ANIMATION_FLAG as u32,
+    };
+    pub const XMP: ContainerFeatures = ContainerFeatures {
+        bits: sys::XMP_FLAG as u32,
+    };
+    pub const EXIF: ContainerFeatures = ContainerFeatures {
+        bits: sys::EXIF_FLAG as u32,
+    };
+    pub const ALPHA: ContainerFeatures = ContainerFeatures {
+        bits: sys::ALPHA_FLAG as u32,
+    };
+    pub const ICC: ContainerFeatures = ContainerFeatures {
+        bits: sys::ICCP_FLAG as u32,
+    };
+
+    pub fn empty() -> Self {
+        ContainerFeatures { bits: 0 }
+    }
+
+    pub fn all() -> Self {
+        Self::ANIMATION | Self::XMP | Self::EXIF | Self::ALPHA | Self::ICC
+    }
+
+    pub fn bits(self) -> u32 {
+        self.bits
+    }
+
+    /// Returns `None` if `bits` contains unknown flags.
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        if bits & !Self::all().bits == 0 {
+            Some(ContainerFeatures { bits })
+        } else {
+            None
+        }
+    }
+
+    /// Drops unknown flags, such as the obsolete fragments flag.
+    pub fn from_bits_truncate(bits: u32) -> Self {
+        ContainerFeatures {
+            bits: bits & Self::all().bits,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn has_alpha(self) -> bool {
+        self.contains(Self::ALPHA)
+    }
+
+    pub fn has_animation(self) -> bool {
+        self.contains(Self::ANIMATION)
+    }
+
+    pub fn has_icc(self) -> bool {
+        self.contains(Self::ICC)
+    }
+
+    pub fn has_exif(self) -> bool {
+        self.contains(Self::EXIF)
+    }
+
+    pub fn has_xmp(self) -> bool {
+        self.contains(Self::XMP)
+    }
+}
+
+impl BitOr for ContainerFeatures {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        ContainerFeatures {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+impl BitOrAssign for ContainerFeatures {
+    fn bitor_assign(&mut self, other: Self) {
+        self.bits |= other.bits;
+    }
+}
+
+impl BitAnd for ContainerFeatures {
+    type Output = Self;
+    fn bitand(self, other: Self) -> Self {
+        ContainerFeatures {
+            bits: self.bits & other.bits,
+        }
+    }
+}
+
+impl BitAndAssign for ContainerFeatures {
+    fn bitand_assign(&mut self, other: Self) {
+        self.bits &= other.bits;
+    }
+}
+
+impl Sub for ContainerFeatures {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        ContainerFeatures {
+            bits: self.bits & !other.bits,
+        }
+    }
+}
+
+impl fmt::Debug for ContainerFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (Self::ANIMATION, "ANIMATION"),
+            (Self::XMP, "XMP"),
+            (Self::EXIF, "EXIF"),
+            (Self::ALPHA, "ALPHA"),
+            (Self::ICC, "ICC"),
+        ];
+        let mut first = true;
+        for &(flag, name) in &names {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        if first {
+            f.write_str("(empty)")?;
+        }
+        Ok(())
+    }
+}
+
+// Frame offsets are stored halved in 24 bits, but `WebPMuxPushFrame` only
+// accepts offsets below 2^24.
+const MAX_POSITION_OFFSET: u32 = 1 << 24;
+const MAX_DURATION: u32 = 1 << 24;
+#[cfg(feature = "0.5")]
+const MAX_CANVAS_SIZE: u32 = 1 << 24;
+
+/// Placement and timing of an animation frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MuxFrameInfo {
+    /// Horizontal offset on the canvas; must be even and less than 2^24.
+    ///
+    /// The container allows offsets up to 2 * (2^24 - 1), but libwebp's
+    /// muxer rejects larger ones, so `validate` does too.
+    pub x_offset: u32,
+    /// Vertical offset on the canvas; same limits as `x_offset`.
+    pub y_offset: u32,
+    /// Display time in milliseconds.
+    pub duration: u32,
+    pub dispose_method: WebPMuxAnimDispose,
+    pub blend_method: WebPMuxAnimBlend,
+}
+
+impl Default for MuxFrameInfo {
+    fn default() -> Self {
+        MuxFrameInfo {
+            x_offset: 0,
+            y_offset: 0,
+            duration: 100,
+            dispose_method: WebPMuxAnimDispose::WEBP_MUX_DISPOSE_NONE,
+            blend_method: WebPMuxAnimBlend::WEBP_MUX_BLEND,
+        }
+    }
+}
+
+impl MuxFrameInfo {
+    fn validate(&self) -> Result<(), WebpError> {
+        if self.x_offset & 1 != 0 || self.y_offset & 1 != 0 {
+            return Err(WebpError::InvalidArgument(format!(
+                "frame offset ({}, {}) should be even",
+                self.x_offset, self.y_offset,
+            )));
+        }
+        if self.x_offset >= MAX_POSITION_OFFSET || self.y_offset >= MAX_POSITION_OFFSET {
+            return Err(WebpError::InvalidArgument(format!(
+                "frame offset ({}, {}) is too large",
+                self.x_offset, self.y_offset,
+            )));
+        }
+        if self.duration >= MAX_DURATION {
+            return Err(WebpError::InvalidArgument(format!(
+                "frame duration {} is too large",
+                self.duration,
+            )));
+        }
+        Ok(())
+    }
+
+    fn into_sys(self, bitstream: sys::WebPData) -> sys::WebPMuxFrameInfo {
+        sys::WebPMuxFrameInfo {
+            bitstream,
+            x_offset: self.x_offset as c_int,
+            y_offset: self.y_offset as c_int,
+            duration: self.duration as c_int,
+            id: sys::WebPChunkId::WEBP_CHUNK_ANMF,
+            dispose_method: self.dispose_method,
+            blend_method: self.blend_method,
+            pad: [0; 1],
+        }
+    }
+}
+
+/// Global parameters of an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MuxAnimParams {
+    /// Background colour, as `0xAARRGGBB`. Viewers may ignore it.
+    pub background_color: u32,
+    /// Number of times to play the animation; 0 loops forever.
+    pub loop_count: u32,
+}
+
+fn check_frame_bounds(
+    info: &MuxFrameInfo,
+    width: u32,
+    height: u32,
+    canvas_width: u32,
+    canvas_height: u32,
+) -> Result<(), WebpError> {
+    if u64::from(info.x_offset) + u64::from(width) > u64::from(canvas_width)
+        || u64::from(info.y_offset) + u64::from(height) > u64::from(canvas_height)
+    {
+        return Err(WebpError::InvalidArgument(format!(
+            "frame {}x{} at ({}, {}) exceeds the {}x{} canvas",
+            width, height, info.x_offset, info.y_offset, canvas_width, canvas_height,
+        )));
+    }
+    Ok(())
+}
+
+// libwebp counts frames from 1 and uses 0 for the last one.
+fn frame_nth(index: u32) -> Result<u32, WebpError> {
+    index
+        .checked_add(1)
+        .ok_or(WebpError::Mux(WebPMuxError::WEBP_MUX_NOT_FOUND))
+}
+
+/// An owned `WebPMux` object: a WebP RIFF container being edited.
+///
+/// Data passed to `from_bytes`, `set_image`, `push_frame` and the chunk
+/// setters is copied, so a `Mux<'static>` does not borrow its inputs. The
+/// `*_borrowed` variants keep pointers to the caller's bitstreams instead,
+/// which must then outlive the mux.
+pub struct Mux<'a> {
+    ptr: *mut sys::WebPMux,
+    // IDs of the chunks libwebp stores as unknown, as it cannot enumerate
+    // them. Parsed files may use IDs that `FourCC` rejects.
+    unknown_ids: Vec<[u8; 4]>,
+    // Application chunks libwebp refused to add, appended by `assemble`.
+    extra_chunks: Vec<(FourCC, Vec<u8>)>,
+    // Canvas size frames are checked against, if known.
+    canvas: Option<(u32, u32)>,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Mux<'a> {
+    /// Creates an empty mux.
+    pub fn new() -> Result<Self, WebpError> {
+        let ptr = unsafe { sys::WebPMuxNew() };
+        if ptr.is_null() {
+            return Err(WebpError::Mux(WebPMuxError::WEBP_MUX_MEMORY_ERROR));
+        }
+        Ok(Mux {
+            ptr,
+            unknown_ids: Vec::new(),
+            extra_chunks: Vec::new(),
+            canvas: None,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Parses a WebP file, either a plain bitstream or a RIFF container.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, WebpError> {
+        Self::create(data, true)
+    }
+
+    /// Parses a WebP file without copying it.
+    pub fn from_bytes_borrowed(data: &'a [u8]) -> Result<Self, WebpError> {
+        Self::create(data, false)
+    }
+
+    fn create(data: &[u8], copy_data: bool) -> Result<Self, WebpError> {
+        let ids = riff_chunk_ids(data);
+        let unknown_ids = ids
+            .iter()
+            .filter(|id| !KNOWN_CHUNK_IDS.contains(id))
+            .cloned()
+            .collect();
+        let has_vp8x = ids.iter().any(|id| id == b"VP8X");
+        let data = webp_data(data);
+        let ptr = unsafe { sys::WebPMuxCreate(&data, copy_data as c_int) };
+        if ptr.is_null() {
+            return Err(WebpError::Mux(WebPMuxError::WEBP_MUX_BAD_DATA));
+        }
+        let mut mux = Mux {
+            ptr,
+            unknown_ids,
+            extra_chunks: Vec::new(),
+            canvas: None,
+            _marker: PhantomData,
+        };
+        if has_vp8x {
+            mux.canvas = mux.canvas_size().ok();
+        }
+        Ok(mux)
+    }
+
+    pub fn as_ptr(&self) -> *const sys::WebPMux {
+        self.ptr
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut sys::WebPMux {
+        self.ptr
+    }
+
+    /// Replaces the image (and any animation frames) with a single VP8/VP8L
+    /// bitstream, optionally wrapped in a RIFF container.
+    pub fn set_image(&mut self, bitstream: &[u8]) -> Result<(), WebpError> {
+        self.set_image_data(bitstream, true)
+    }
+
+    /// Like `set_image`, without copying the bitstream.
+    pub fn set_image_borrowed(&mut self, bitstream: &'a [u8]) -> Result<(), WebpError> {
+        self.set_image_data(bitstream, false)
+    }
+
+    fn set_image_data(&mut self, bitstream: &[u8], copy_data: bool) -> Result<(), WebpError> {
+        let bitstream = webp_data(bitstream);
+        mux_result(unsafe { sys::WebPMuxSetImage(self.ptr, &bitstream, copy_data as c_int) })
+    }
+
+    fn get_chunk(&self, fourcc: &[u8; 4]) -> Option<&[u8]> {
+        let mut data: sys::WebPData = unsafe { mem::zeroed() };
+        let res =
+            unsafe { sys::WebPMuxGetChunk(self.ptr, fourcc.as_ptr() as *const c_char, &mut data) };
+        if res != WebPMuxError::WEBP_MUX_OK {
+            return None;
+        }
+        if data.bytes.is_null() {
+            // empty chunks have no storage
+            return Some(&[]);
+        }
+        Some(unsafe { slice::from_raw_parts(data.bytes, data.size) })
+    }
+
+    fn set_chunk(&mut self, fourcc: &[u8; 4], data: &[u8]) -> Result<(), WebpError> {
+        let data = webp_data(data);
+        mux_result(unsafe {
+            sys::WebPMuxSetChunk(self.ptr, fourcc.as_ptr() as *const c_char, &data, 1)
+        })
+    }
+
+    fn delete_chunk(&mut self, fourcc: &[u8; 4]) -> Result<bool, WebpError> {
+        match unsafe { sys::WebPMuxDeleteChunk(self.ptr, fourcc.as_ptr() as *const c_char) } {
+            WebPMuxError::WEBP_MUX_NOT_FOUND => Ok(false),
+            err => mux_result(err).map(|()| true),
+        }
+    }
+
+    /// Returns the ICC colour profile from the `ICCP` chunk, if any.
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.get_chunk(b"ICCP")
+    }
+
+    /// Sets the ICC colour profile, replacing any previous one.
+    pub fn set_icc_profile(&mut self, profile: &[u8]) -> Result<(), WebpError> {
+        self.set_chunk(b"ICCP", profile)
+    }
+
+    /// Removes the ICC colour profile. Returns whether there was one.
+    pub fn delete_icc_profile(&mut self) -> Result<bool, WebpError> {
+        self.delete_chunk(b"ICCP")
+    }
+
+    /// Returns the raw `EXIF` chunk, if any. See `ExifInfo::parse`.
+    pub fn exif(&self) -> Option<&[u8]> {
+        self.get_chunk(b"EXIF")
+    }
+
+    /// Parses the orientation and date tags of the `EXIF` chunk.
+    pub fn exif_info(&self) -> Option<Result<ExifInfo, WebpError>> {
+        self.exif().map(ExifInfo::parse)
+    }
+
+    /// Sets the `EXIF` chunk, replacing any previous one.
+    pub fn set_exif(&mut self, exif: &[u8]) -> Result<(), WebpError> {
+        self.set_chunk(b"EXIF", exif)
+    }
+
+    /// Removes the `EXIF` chunk. Returns whether there was one.
+    pub fn delete_exif(&mut self) -> Result<bool, WebpError> {
+        self.delete_chunk(b"EXIF")
+    }
+
+    /// Returns the XMP metadata packet from the `XMP ` chunk, if any.
+    pub fn xmp(&self) -> Option<&[u8]> {
+        self.get_chunk(b"XMP ")
+    }
+
+    /// Sets the XMP metadata packet, replacing any previous one.
+    ///
+    /// The `XMP_FLAG` of the VP8X header is updated on `assemble`.
+    pub fn set_xmp(&mut self, xmp: &[u8]) -> Result<(), WebpError> {
+        self.set_chunk(b"XMP ", xmp)
+    }
+
+    /// Removes the XMP metadata. Returns whether there was one.
+    pub fn delete_xmp(&mut self) -> Result<bool, WebpError> {
+        self.delete_chunk(b"XMP ")
+    }
+
+    /// Returns the payload of the first application chunk with the ID.
+    pub fn unknown_chunk(&self, id: FourCC) -> Option<&[u8]> {
+        self.get_chunk(&id.0).or_else(|| {
+            self.extra_chunks
+                .iter()
+                .find(|&&(other, _)| other == id)
+                .map(|(_, data)| &data[..])
+        })
+    }
+
+    /// Sets an application chunk, replacing any chunks with the same ID.
+    ///
+    /// libwebp can only add a single application chunk to a mux. Further
+    /// chunks are kept aside and appended to the file by `assemble`.
+    pub fn set_unknown_chunk(&mut self, id: FourCC, data: &[u8]) -> Result<(), WebpError> {
+        if data.len() as u64 > u64::from(u32::max_value()) {
+            return Err(WebpError::Mux(WebPMuxError::WEBP_MUX_INVALID_ARGUMENT));
+        }
+        let has_others = self
+            .present_unknown_ids()
+            .iter()
+            .any(|other| other != id.as_bytes());
+        if has_others {
+            self.delete_chunk(&id.0)?;
+            self.extra_chunks.retain(|&(other, _)| other != id);
+            self.extra_chunks.push((id, data.to_vec()));
+        } else {
+            self.extra_chunks.retain(|&(other, _)| other != id);
+            self.set_chunk(&id.0, data)?;
+        }
+        // replaced chunks move to the end, like in the assembled file
+        self.unknown_ids.retain(|other| other != id.as_bytes());
+        self.unknown_ids.push(id.0);
+        Ok(())
+    }
+
+    /// Removes all application chunks with the ID. Returns whether there
+    /// were any.
+    pub fn delete_unknown_chunk(&mut self, id: FourCC) -> Result<bool, WebpError> {
+        self.unknown_ids.retain(|other| other != id.as_bytes());
+        let num_extra = self.extra_chunks.len();
+        self.extra_chunks.retain(|&(other, _)| other != id);
+        let deleted = self.delete_chunk(&id.0)?;
+        Ok(deleted || self.extra_chunks.len() != num_extra)
+    }
+
+    /// Lists the IDs of the application chunks, without duplicates.
+    pub fn unknown_chunk_ids(&self) -> Vec<FourCC> {
+        self.present_unknown_ids()
+            .iter()
+            .filter_map(|id| FourCC::new(id).ok())
+            .collect()
+    }
+
+    // Removes every chunk libwebp stores as unknown, including those whose
+    // IDs `FourCC` rejects, and the chunks kept aside for `assemble`.
+    fn delete_all_unknown_chunks(&mut self) -> Result<(), WebpError> {
+        for id in self.present_unknown_ids() {
+            self.delete_chunk(&id)?;
+        }
+        self.unknown_ids.clear();
+        self.extra_chunks.clear();
+        Ok(())
+    }
+
+    // Lists the IDs of all chunks libwebp stores as unknown or that are kept
+    // aside for `assemble`, without duplicates.
+    fn present_unknown_ids(&self) -> Vec<[u8; 4]> {
+        let mut ids: Vec<[u8; 4]> = Vec::new();
+        for id in &self.unknown_ids {
+            let present = self.get_chunk(id).is_some()
+                || self.extra_chunks.iter().any(|(other, _)| &other.0 == id);
+            if !ids.contains(id) && present {
+                ids.push(*id);
+            }
+        }
+        ids
+    }
+
+    /// Returns the number of animation frames.
+    pub fn num_frames(&self) -> Result<u32, WebpError> {
+        let mut num: c_int = 0;
+        mux_result(unsafe {
+            sys::WebPMuxNumChunks(self.ptr, sys::WebPChunkId::WEBP_CHUNK_ANMF, &mut num)
+        })?;
+        Ok(num as u32)
+    }
+
+    /// Appends an animation frame from an encoded image.
+    ///
+    /// Offsets must be even, and the frame must fit in the canvas if one was
+    /// set with `set_canvas_size` or read from the input file.
+    pub fn push_frame(&mut self, bitstream: &[u8], info: &MuxFrameInfo) -> Result<(), WebpError> {
+        self.push_frame_data(bitstream, info, true)
+    }
+
+    /// Like `push_frame`, without copying the bitstream.
+    pub fn push_frame_borrowed(
+        &mut self,
+        bitstream: &'a [u8],
+        info: &MuxFrameInfo,
+    ) -> Result<(), WebpError> {
+        self.push_frame_data(bitstream, info, false)
+    }
+
+    fn push_frame_data(
+        &mut self,
+        bitstream: &[u8],
+        info: &MuxFrameInfo,
+        copy_data: bool,
+    ) -> Result<(), WebpError> {
+        info.validate()?;
+        let (width, height) = WebPGetInfo(bitstream)?;
+        if let Some((canvas_width, canvas_height)) = self.canvas {
+            check_frame_bounds(info, width, height, canvas_width, canvas_height)?;
+        }
+        let frame = info.into_sys(webp_data(bitstream));
+        mux_result(unsafe { sys::WebPMuxPushFrame(self.ptr, &frame, copy_data as c_int) })?;
+        // animated files need an ANIM chunk; give them defaults until set
+        match self.animation_params() {
+            Err(WebpError::Mux(WebPMuxError::WEBP_MUX_NOT_FOUND)) => {
+                self.set_animation_params(&MuxAnimParams::default())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Returns the encoded image and the parameters of the `index`-th frame
+    /// (counting from 0).
+    pub fn frame(&self, index: u32) -> Result<(WebpBox<[u8]>, MuxFrameInfo), WebpError> {
+        let nth = frame_nth(index)?;
+        let mut frame: sys::WebPMuxFrameInfo = unsafe { mem::zeroed() };
+        mux_result(unsafe { sys::WebPMuxGetFrame(self.ptr, nth, &mut frame) })?;
+        if frame.bitstream.bytes.is_null() {
+            return Err(WebpError::Unknown);
+        }
+        let bitstream = unsafe {
+            WebpBox::from_raw_parts(frame.bitstream.bytes as *mut u8, frame.bitstream.size)
+        };
+        let info = MuxFrameInfo {
+            x_offset: frame.x_offset as u32,
+            y_offset: frame.y_offset as u32,
+            duration: frame.duration as u32,
+            dispose_method: frame.dispose_method,
+            blend_method: frame.blend_method,
+        };
+        Ok((bitstream, info))
+    }
+
+    /// Removes the `index`-th frame (counting from 0).
+    pub fn delete_frame(&mut self, index: u32) -> Result<(), WebpError> {
+        let nth = frame_nth(index)?;
+        mux_result(unsafe { sys::WebPMuxDeleteFrame(self.ptr, nth) })
+    }
+
+    pub fn animation_params(&self) -> Result<MuxAnimParams, WebpError> {
+        let mut params = sys::WebPMuxAnimParams {
+            bgcolor: 0,
+            loop_count: 0,
+        };
+        mux_result(unsafe { sys::WebPMuxGetAnimationParams(self.ptr, &mut params) })?;
+        Ok(MuxAnimParams {
+            background_color: params.bgcolor,
+            loop_count: params.loop_count as u32,
+        })
+    }
+
+    pub fn set_animation_params(&mut self, params: &MuxAnimParams) -> Result<(), WebpError> {
+        if params.loop_count >= 1 << 16 {
+            return Err(WebpError::InvalidArgument(format!(
+                "loop count {} should be less than 65536",
+                params.loop_count,
+            )));
+        }
+        let params = sys::WebPMuxAnimParams {
+            bgcolor: params.background_color,
+            loop_count: params.loop_count as c_int,
+        };
+        mux_result(unsafe { sys::WebPMuxSetAnimationParams(self.ptr, &params) })
+    }
+
+    /// Sets the canvas size. Existing frames must fit in it.
+    #[cfg(feature = "0.5")]
+    pub fn set_canvas_size(&mut self, width: u32, height: u32) -> Result<(), WebpError> {
+        if width == 0 || height == 0 || width > MAX_CANVAS_SIZE || height > MAX_CANVAS_SIZE {
+            return Err(WebpError::InvalidArgument(format!(
+                "canvas {}x{} should be between 1x1 and {}x{}",
+                width, height, MAX_CANVAS_SIZE, MAX_CANVAS_SIZE,
+            )));
+        }
+        for index in 0..self.num_frames()? {
+            let (bitstream, info) = self.frame(index)?;
+            let (frame_width, frame_height) = WebPGetInfo(&bitstream)?;
+            check_frame_bounds(&info, frame_width, frame_height, width, height)?;
+        }
+        mux_result(unsafe {
+            sys::WebPMuxSetCanvasSize(self.ptr, width as c_int, height as c_int)
+        })?;
+        self.canvas = Some((width, height));
+        Ok(())
+    }
+
+    /// Returns the canvas size.
+    pub fn canvas_size(&self) -> Result<(u32, u32), WebpError> {
+        let mut width: c_int = 0;
+        let mut height: c_int = 0;
+        mux_result(unsafe { sys::WebPMuxGetCanvasSize(self.ptr, &mut width, &mut height) })?;
+        Ok((width as u32, height as u32))
+    }
+
+    /// Returns the feature flags of the container.
+    ///
+    /// They are read from the `VP8X` chunk, which libwebp only rewrites when
+    /// assembling; call this on a parsed file to see the effect of edits.
+    pub fn features(&self) -> Result<ContainerFeatures, WebpError> {
+        let mut flags = 0;
+        mux_result(unsafe { sys::WebPMuxGetFeatures(self.ptr, &mut flags) })?;
+        Ok(ContainerFeatures::from_bits_truncate(flags))
+    }
+
+    /// Assembles the container into a WebP file.
+    pub fn assemble(&mut self) -> Result<WebpBox<[u8]>, WebpError> {
+        let mut data: sys::WebPData = unsafe { mem::zeroed() };
+        mux_result(unsafe { sys::WebPMuxAssemble(self.ptr, &mut data) })?;
+        if data.bytes.is_null() {
+            return Err(WebpError::Unknown);
+        }
+        let riff = unsafe { WebpBox::from_raw_parts(data.bytes as *mut u8, data.size) };
+        if self.extra_chunks.is_empty() {
+            return Ok(riff);
+        }
+        let riff = append_chunks(&riff, &self.extra_chunks)?;
+        // `WebpBox` releases its buffer with `WebPFree`, so hand it a malloc'ed copy
+        let mut data: sys::WebPData = unsafe { mem::zeroed() };
+        if unsafe { sys::WebPDataCopy(&webp_data(&riff), &mut data) } == 0 {
+            return Err(WebpError::Mux(WebPMuxError::WEBP_MUX_MEMORY_ERROR));
+        }
+        Ok(unsafe { WebpBox::from_raw_parts(data.bytes as *mut u8, data.size) })
+    }
+}
+
+// Appends chunks to a RIFF file and updates the RIFF size.
+fn append_chunks(riff: &[u8], chunks: &[(FourCC, Vec<u8>)]) -> Result<Vec<u8>, WebpError> {
+    fn le32(value: u32) -> [u8; 4] {
+        [
+            value as u8,
+            (value >> 8) as u8,
+            (value >> 16) as u8,
+            (value >> 24) as u8,
+        ]
+    }
+    let padded_len = |data: &[u8]| 8 + data.len() as u64 + (data.len() as u64 & 1);
+    let riff_size = chunks
+        .iter()
+        .fold(riff.len() as u64 - 8, |size, (_, data)| {
+            size + padded_len(data)
+        });
+    if riff_size > u64::from(u32::max_value()) {
+        return Err(WebpError::Mux(WebPMuxError::WEBP_MUX_INVALID_ARGUMENT));
+    }
+    let mut out = Vec::with_capacity(riff_size as usize + 8);
+    out.extend_from_slice(&riff[..4]);
+    out.extend_from_slice(&le32(riff_size as u32));
+    out.extend_from_slice(&riff[8..]);
+    for &(id, ref data) in chunks {
+        out.extend_from_slice(&id.0);
+        out.extend_from_slice(&le32(data.len() as u32));
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+impl<'a> Drop for Mux<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::WebPMuxDelete(self.ptr);
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Mux<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mux")
+            .field("canvas_size", &self.canvas_size().ok())
+            .finish()
+    }
+}
+
+/// Selects the chunks removed by `strip_metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StripOptions {
+    pub exif: bool,
+    pub xmp: bool,
+    pub icc: bool,
+    /// Application chunks; see `FourCC`.
+    pub unknown: bool,
+}
+
+impl Default for StripOptions {
+    /// Strips all metadata.
+    fn default() -> Self {
+        StripOptions {
+            exif: true,
+            xmp: true,
+            icc: true,
+            unknown: true,
+        }
+    }
+}
+
+/// Removes metadata chunks from a WebP file without re-encoding.
+///
+/// The image data is copied unchanged and the VP8X flags are updated to match
+/// the remaining chunks.
+pub fn strip_metadata(data: &[u8], options: &StripOptions) -> Result<WebpBox<[u8]>, WebpError> {
+    let mut mux = Mux::from_bytes_borrowed(data)?;
+    if options.exif {
+        mux.delete_exif()?;
+    }
+    if options.xmp {
+        mux.delete_xmp()?;
+    }
+    if options.icc {
+        mux.delete_icc_profile()?;
+    }
+    if options.unknown {
+        mux.delete_all_unknown_chunks()?;
+    }
+    mux.assemble()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use decode::{WebPDecodeRGBA, WebPGetFeatures, WebPGetInfo};
+    use encode::WebPEncodeLosslessRGBA;
+    use exif::ExifOrientation;
+    use test_utils::{solid_frame, test_cases};
+
+    #[test]
+    fn test_get_mux_version() {
+        assert!(WebPGetMuxVersion() >= 0x000100);
+    }
+
+    #[test]
+    fn test_mux_from_bytes() {
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            let info = WebPGetInfo(&case.webp_data).unwrap();
+            assert_eq!(mux.canvas_size().unwrap(), info);
+            let webp = mux.assemble().unwrap();
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_mux_build() {
+        let rgba = [255, 0, 0, 255, 0, 255, 0, 128];
+        let image = WebPEncodeLosslessRGBA(&rgba, 2, 1, 8).unwrap();
+        let mut mux = Mux::new().unwrap();
+        assert!(mux.assemble().is_err());
+        mux.set_image(&image).unwrap();
+        assert_eq!(mux.canvas_size().unwrap(), (2, 1));
+        let webp = mux.assemble().unwrap();
+        assert_eq!(&WebPDecodeRGBA(&webp).unwrap().2[..], &rgba[..]);
+
+        for case in test_cases() {
+            mux.set_image(&case.webp_data).unwrap();
+            let webp = mux.assemble().unwrap();
+            assert_eq!(WebPGetInfo(&webp), WebPGetInfo(&case.webp_data));
+        }
+    }
+
+    #[test]
+    fn test_mux_errors() {
+        assert_eq!(
+            Mux::from_bytes(b"RIFF\x04\x00\x00\x00WEBP").unwrap_err(),
+            WebpError::Mux(WebPMuxError::WEBP_MUX_BAD_DATA)
+        );
+        let mut mux = Mux::new().unwrap();
+        assert_eq!(
+            mux.set_image(b"garbage").unwrap_err(),
+            WebpError::Mux(WebPMuxError::WEBP_MUX_INVALID_ARGUMENT)
+        );
+        let err = mux.assemble().unwrap_err();
+        assert!(err.to_string().starts_with("webp mux error: "));
+    }
+
+    #[test]
+    fn test_mux_icc_profile() {
+        let profile = b"\x00\x00\x02\x0clcms\x02\x10\x00\x00mntrRGB XYZ ";
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            assert_eq!(mux.icc_profile(), None);
+            assert!(!mux.delete_icc_profile().unwrap());
+            mux.set_icc_profile(profile).unwrap();
+            assert_eq!(mux.icc_profile(), Some(&profile[..]));
+            let webp = mux.assemble().unwrap();
+
+            let mut flags = 0;
+            let mut mux = Mux::from_bytes(&webp).unwrap();
+            unsafe {
+                sys::WebPMuxGetFeatures(mux.as_ptr(), &mut flags);
+            }
+            assert_ne!(flags & sys::ICCP_FLAG as u32, 0);
+            assert_eq!(mux.icc_profile(), Some(&profile[..]));
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+
+            mux.set_icc_profile(b"other").unwrap();
+            assert_eq!(mux.icc_profile(), Some(&b"other"[..]));
+            assert!(mux.delete_icc_profile().unwrap());
+            assert_eq!(mux.icc_profile(), None);
+            let webp = mux.assemble().unwrap();
+            assert_eq!(Mux::from_bytes(&webp).unwrap().icc_profile(), None);
+        }
+    }
+
+    #[test]
+    fn test_mux_exif() {
+        // II, IFD0 with a single orientation entry (rotate 270)
+        let exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x08\0\0\0\0\0\0\0";
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            assert!(mux.exif().is_none());
+            assert!(mux.exif_info().is_none());
+            mux.set_exif(exif).unwrap();
+            let webp = mux.assemble().unwrap();
+
+            let mut mux = Mux::from_bytes(&webp).unwrap();
+            assert_eq!(mux.exif(), Some(&exif[..]));
+            let info = mux.exif_info().unwrap().unwrap();
+            assert_eq!(info.orientation, Some(ExifOrientation::Rotate270));
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+
+            mux.set_exif(b"not a TIFF").unwrap();
+            assert!(mux.exif_info().unwrap().is_err());
+            assert!(mux.delete_exif().unwrap());
+            assert!(!mux.delete_exif().unwrap());
+        }
+    }
+
+    fn mux_flags(webp: &[u8]) -> u32 {
+        let mux = Mux::from_bytes(webp).unwrap();
+        let mut flags = 0;
+        mux_result(unsafe { sys::WebPMuxGetFeatures(mux.as_ptr(), &mut flags) }).unwrap();
+        flags
+    }
+
+    #[test]
+    fn test_mux_xmp() {
+        let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF/></x:xmpmeta>"#;
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            assert_eq!(mux.xmp(), None);
+            mux.set_xmp(xmp).unwrap();
+            assert_eq!(mux.xmp(), Some(&xmp[..]));
+            let webp = mux.assemble().unwrap();
+            assert_ne!(mux_flags(&webp) & sys::XMP_FLAG as u32, 0);
+
+            let features = WebPGetFeatures(&webp).unwrap();
+            let (width, height) = WebPGetInfo(&case.webp_data).unwrap();
+            assert_eq!((features.width(), features.height()), (width, height));
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+
+            unsafe {
+                let data = webp_data(&webp);
+                let demux = sys::WebPDemux(&data);
+                assert!(!demux.is_null());
+                let flags = sys::WebPDemuxGetI(demux, sys::WebPFormatFeature::WEBP_FF_FORMAT_FLAGS);
+                assert_ne!(flags & sys::XMP_FLAG as u32, 0);
+                let mut iter = mem::zeroed();
+                let found =
+                    sys::WebPDemuxGetChunk(demux, b"XMP ".as_ptr() as *const c_char, 1, &mut iter);
+                assert_ne!(found, 0);
+                assert_eq!(
+                    slice::from_raw_parts(iter.chunk.bytes, iter.chunk.size),
+                    &xmp[..]
+                );
+                sys::WebPDemuxReleaseChunkIterator(&mut iter);
+                sys::WebPDemuxDelete(demux);
+            }
+
+            let mut mux = Mux::from_bytes(&webp).unwrap();
+            assert_eq!(mux.xmp(), Some(&xmp[..]));
+            assert!(mux.delete_xmp().unwrap());
+            let webp = mux.assemble().unwrap();
+            assert_eq!(mux_flags(&webp) & sys::XMP_FLAG as u32, 0);
+            assert_eq!(Mux::from_bytes(&webp).unwrap().xmp(), None);
+        }
+    }
+
+    #[test]
+    fn test_fourcc() {
+        assert_eq!(FourCC::new(b"pHsh").unwrap().as_bytes(), b"pHsh");
+        assert_eq!(FourCC::new(b"ORG ").unwrap().to_string(), "ORG ");
+        for id in &FourCC::RESERVED {
+            assert!(FourCC::new(id).is_err(), "{:?}", id);
+        }
+        assert!(FourCC::new(b"ab\0c").is_err());
+        assert!(FourCC::new(b"\xffabc").is_err());
+    }
+
+    #[test]
+    fn test_riff_chunk_ids() {
+        for case in test_cases() {
+            let ids = riff_chunk_ids(&case.webp_data);
+            assert_eq!(ids.len(), 1);
+            assert!(&ids[0] == b"VP8 " || &ids[0] == b"VP8L");
+        }
+        assert!(riff_chunk_ids(b"RIFF").is_empty());
+        // odd-sized chunks are padded; truncated ones are dropped
+        let data = b"RIFF\0\0\0\0WEBPabcd\x01\0\0\0x\0efgh\x00\0\0\0ijkl\xff\xff\xff\xff";
+        assert_eq!(riff_chunk_ids(data), vec![*b"abcd", *b"efgh", *b"ijkl"]);
+    }
+
+    #[test]
+    fn test_mux_unknown_chunks() {
+        let hash = FourCC::new(b"pHsh").unwrap();
+        let origin = FourCC::new(b"ORIG").unwrap();
+        let mut empty = Mux::new().unwrap();
+        empty.set_unknown_chunk(hash, b"").unwrap();
+        empty.set_unknown_chunk(origin, b"x").unwrap();
+        assert_eq!(empty.unknown_chunk_ids(), vec![hash, origin]);
+        assert_eq!(empty.unknown_chunk(origin), Some(&b"x"[..]));
+        assert!(empty.delete_unknown_chunk(origin).unwrap());
+        assert_eq!(empty.unknown_chunk_ids(), vec![hash]);
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            assert!(mux.unknown_chunk_ids().is_empty());
+            mux.set_unknown_chunk(hash, b"\x01\x02\x03").unwrap();
+            mux.set_unknown_chunk(origin, b"upload-42").unwrap();
+            mux.set_unknown_chunk(hash, b"\x04\x05").unwrap();
+            assert_eq!(mux.unknown_chunk_ids(), vec![origin, hash]);
+            let webp = mux.assemble().unwrap();
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+
+            let mut mux = Mux::from_bytes(&webp).unwrap();
+            assert_eq!(mux.unknown_chunk_ids(), vec![origin, hash]);
+            assert_eq!(mux.unknown_chunk(hash), Some(&b"\x04\x05"[..]));
+            assert_eq!(mux.unknown_chunk(origin), Some(&b"upload-42"[..]));
+            assert!(mux.delete_unknown_chunk(hash).unwrap());
+            assert!(!mux.delete_unknown_chunk(hash).unwrap());
+            assert_eq!(mux.unknown_chunk(hash), None);
+            assert_eq!(mux.unknown_chunk_ids(), vec![origin]);
+            let webp = mux.assemble().unwrap();
+            assert_eq!(
+                Mux::from_bytes(&webp).unwrap().unknown_chunk_ids(),
+                vec![origin]
+            );
+        }
+    }
+
+    // Adds chunks with IDs that `FourCC` rejects to a container.
+    fn with_foreign_chunks(webp: &[u8]) -> Vec<u8> {
+        let mut chunks = vec![(FourCC(*b"\x01abc"), b"binary".to_vec())];
+        if cfg!(feature = "0.6") {
+            // older versions of libwebp parse fragments themselves
+            chunks.push((FourCC(*b"FRGM"), b"fragment".to_vec()));
+        }
+        append_chunks(webp, &chunks).unwrap()
+    }
+
+    fn has_foreign_chunks(webp: &[u8]) -> bool {
+        riff_chunk_ids(webp)
+            .iter()
+            .any(|id| id == b"\x01abc" || id == b"FRGM")
+    }
+
+    #[test]
+    fn test_mux_foreign_chunks() {
+        let origin = FourCC::new(b"ORIG").unwrap();
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            mux.set_icc_profile(b"icc").unwrap();
+            let webp = with_foreign_chunks(&mux.assemble().unwrap());
+
+            let mut mux = Mux::from_bytes(&webp).unwrap();
+            assert!(mux.unknown_chunk_ids().is_empty());
+            mux.set_unknown_chunk(origin, b"upload-42").unwrap();
+            assert_eq!(mux.unknown_chunk_ids(), vec![origin]);
+            let webp = mux.assemble().unwrap();
+            assert!(has_foreign_chunks(&webp));
+            let mux = Mux::from_bytes(&webp).unwrap();
+            assert_eq!(mux.unknown_chunk(origin), Some(&b"upload-42"[..]));
+            assert_eq!(
+                WebPDecodeRGBA(&webp).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+        }
+    }
+
+    fn chunk_payload<'a>(data: &'a [u8], id: &[u8; 4]) -> Option<&'a [u8]> {
+        let mut offset = 12;
+        while offset + 8 <= data.len() {
+            let size = data[offset + 4] as usize
+                | (data[offset + 5] as usize) << 8
+                | (data[offset + 6] as usize) << 16
+                | (data[offset + 7] as usize) << 24;
+            if &data[offset..offset + 4] == id {
+                return data.get(offset + 8..offset + 8 + size);
+            }
+            offset += 8 + size + (size & 1);
+        }
+        None
+    }
+
+    fn image_payload(data: &[u8]) -> &[u8] {
+        chunk_payload(data, b"VP8 ")
+            .or_else(|| chunk_payload(data, b"VP8L"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_strip_metadata() {
+        let exif = b"II*\0\x08\0\0\0\0\0\0\0\0\0";
+        let origin = FourCC::new(b"ORIG").unwrap();
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            mux.set_exif(exif).unwrap();
+            mux.set_xmp(b"<x:xmpmeta/>").unwrap();
+            mux.set_icc_profile(b"icc").unwrap();
+            mux.set_unknown_chunk(origin, b"upload-42").unwrap();
+            let tagged = mux.assemble().unwrap();
+            let all_flags =
+                (sys::EXIF_FLAG as u32) | (sys::XMP_FLAG as u32) | (sys::ICCP_FLAG as u32);
+            assert_eq!(mux_flags(&tagged) & all_flags, all_flags);
+
+            let stripped = strip_metadata(&tagged, &StripOptions::default()).unwrap();
+            assert_eq!(mux_flags(&stripped) & all_flags, 0);
+            assert_eq!(image_payload(&stripped), image_payload(&case.webp_data));
+            let mux = Mux::from_bytes(&stripped).unwrap();
+            assert!(mux.exif().is_none() && mux.xmp().is_none() && mux.icc_profile().is_none());
+            assert!(mux.unknown_chunk_ids().is_empty());
+            assert_eq!(&stripped[..], &case.webp_data[..]);
+
+            let options = StripOptions {
+                exif: true,
+                xmp: true,
+                icc: false,
+                unknown: false,
+            };
+            let stripped = strip_metadata(&tagged, &options).unwrap();
+            assert_eq!(mux_flags(&stripped) & all_flags, sys::ICCP_FLAG as u32);
+            assert_eq!(image_payload(&stripped), image_payload(&case.webp_data));
+            let mux = Mux::from_bytes(&stripped).unwrap();
+            assert!(mux.exif().is_none() && mux.xmp().is_none());
+            assert_eq!(mux.icc_profile(), Some(&b"icc"[..]));
+            assert_eq!(mux.unknown_chunk(origin), Some(&b"upload-42"[..]));
+            assert_eq!(
+                WebPDecodeRGBA(&stripped).unwrap(),
+                WebPDecodeRGBA(&case.webp_data).unwrap()
+            );
+        }
+        assert!(strip_metadata(b"RIFF", &StripOptions::default()).is_err());
+
+        for case in test_cases() {
+            let mut mux = Mux::from_bytes(&case.webp_data).unwrap();
+            mux.set_icc_profile(b"icc").unwrap();
+            let tagged = with_foreign_chunks(&mux.assemble().unwrap());
+            assert!(has_foreign_chunks(&tagged));
+            let stripped = strip_metadata(&tagged, &StripOptions::default()).unwrap();
+            assert!(!has_foreign_chunks(&stripped));
+            assert_eq!(&stripped[..], &case.webp_data[..]);
+        }
+    }
+
+    #[test]
+    fn test_mux_frames() {
+        let red = solid_frame(4, 4, [255, 0, 0, 255]);
+        let green = solid_frame(2, 6, [0, 255, 0, 255]);
+        let blue = solid_frame(8, 8, [0, 0, 255, 128]);
+
+        let mut mux = Mux::new().unwrap();
+        mux.push_frame(&red, &MuxFrameInfo::default()).unwrap();
+        let second = MuxFrameInfo {
+            x_offset: 6,
+            y_offset: 2,
+            duration: 250,
+            dispose_method: WebPMuxAnimDispose::WEBP_MUX_DISPOSE_BACKGROUND,
+            blend_method: WebPMuxAnimBlend::WEBP_MUX_NO_BLEND,
+        };
+        mux.push_frame(&green, &second).unwrap();
+        mux.push_frame(&blue, &MuxFrameInfo::default()).unwrap();
+        assert_eq!(mux.num_frames().unwrap(), 3);
+        let params = MuxAnimParams {
+            background_color: 0xff20_4060,
+            loop_count: 3,
+        };
+        mux.set_animation_params(&params).unwrap();
+        mux.delete_frame(2).unwrap();
+        assert!(mux.delete_frame(2).is_err());
+        let webp = mux.assemble().unwrap();
+        assert!(WebPGetFeatures(&webp).unwrap().has_animation());
+        // canvas spans all frames
+        assert_eq!(WebPGetInfo(&webp).unwrap(), (8, 8));
+
+        let mux = Mux::from_bytes(&webp).unwrap();
+        assert_eq!(mux.num_frames().unwrap(), 2);
+        assert_eq!(mux.animation_params().unwrap(), params);
+        let (bitstream, info) = mux.frame(1).unwrap();
+        assert_eq!(info, second);
+        assert_eq!(WebPGetInfo(&bitstream).unwrap(), (2, 6));
+        assert_eq!(WebPDecodeRGBA(&bitstream).unwrap().2[..4], [0, 255, 0, 255]);
+        assert!(mux.frame(2).is_err());
+    }
+
+    #[test]
+    fn test_mux_frames_invalid() {
+        let frame = solid_frame(4, 4, [255, 0, 0, 255]);
+        let mut mux = Mux::new().unwrap();
+        let odd = MuxFrameInfo {
+            x_offset: 3,
+            ..MuxFrameInfo::default()
+        };
+        assert!(mux.push_frame(&frame, &odd).is_err());
+        let far = MuxFrameInfo {
+            y_offset: MAX_POSITION_OFFSET - 2,
+            ..MuxFrameInfo::default()
+        };
+        assert!(far.validate().is_ok());
+        mux.push_frame(&frame, &far).unwrap();
+        mux.delete_frame(0).unwrap();
+        // libwebp has a lower limit than the container format
+        let too_far = MuxFrameInfo {
+            y_offset: MAX_POSITION_OFFSET,
+            ..MuxFrameInfo::default()
+        };
+        match mux.push_frame(&frame, &too_far) {
+            Err(WebpError::InvalidArgument(_)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(mux
+            .push_frame(b"garbage", &MuxFrameInfo::default())
+            .is_err());
+        let params = MuxAnimParams {
+            background_color: 0,
+            loop_count: 1 << 16,
+        };
+        assert!(mux.set_animation_params(&params).is_err());
+        assert_eq!(mux.num_frames().unwrap(), 0);
+
+        // a still image cannot get frames
+        let mut still = Mux::from_bytes(&frame).unwrap();
+        assert!(still.push_frame(&frame, &MuxFrameInfo::default()).is_err());
+    }
+
+    #[cfg(feature = "0.5")]
+    #[test]
+    fn test_mux_canvas_size() {
+        let frame = solid_frame(4, 4, [255, 0, 0, 255]);
+        let mut mux = Mux::new().unwrap();
+        assert!(mux.set_canvas_size(0, 10).is_err());
+        mux.set_canvas_size(10, 6).unwrap();
+        let inside = MuxFrameInfo {
+            x_offset: 6,
+            y_offset: 2,
+            ..MuxFrameInfo::default()
+        };
+        mux.push_frame(&frame, &inside).unwrap();
+        let outside = MuxFrameInfo {
+            x_offset: 8,
+            ..MuxFrameInfo::default()
+        };
+        assert!(mux.push_frame(&frame, &outside).is_err());
+        // the existing frame does not fit
+        assert!(mux.set_canvas_size(8, 8).is_err());
+        let webp = mux.assemble().unwrap();
+        assert_eq!(WebPGetInfo(&webp).unwrap(), (10, 6));
+
+        // the canvas of a parsed file is enforced too
+        let mut mux = Mux::from_bytes(&webp).unwrap();
+        assert!(mux.push_frame(&frame, &outside).is_err());
+        mux.push_frame(&frame, &MuxFrameInfo::default()).unwrap();
+        assert_eq!(mux.num_frames().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_container_features() {
+        let flags = ContainerFeatures::ALPHA | ContainerFeatures::EXIF;
+        assert!(flags.has_alpha() && flags.has_exif());
+        assert!(!flags.has_animation() && !flags.has_icc() && !flags.has_xmp());
+        assert_eq!(flags - ContainerFeatures::ALPHA, ContainerFeatures::EXIF);
+        assert_eq!(flags & ContainerFeatures::ICC, ContainerFeatures::empty());
+        assert_eq!(format!("{:?}", flags), "EXIF | ALPHA");
+        assert_eq!(format!("{:?}", ContainerFeatures::empty()), "(empty)");
+        assert_eq!(ContainerFeatures::all().bits(), 0x3e);
+        assert_eq!(ContainerFeatures::from_bits(0x01), None);
+        assert_eq!(
+            ContainerFeatures::from_bits_truncate(0x03),
+            ContainerFeatures::ANIMATION
+        );
+
+        let frame = solid_frame(4, 4, [255, 0, 0, 128]);
+        let mut mux = Mux::from_bytes(&frame).unwrap();
+        assert_eq!(mux.features().unwrap(), ContainerFeatures::ALPHA);
+        mux.set_icc_profile(b"icc").unwrap();
+        mux.set_exif(b"II*\0\x08\0\0\0\0\0\0\0\0\0").unwrap();
+        mux.set_xmp(b"<x:xmpmeta/>").unwrap();
+        let webp = mux.assemble().unwrap();
+        let features = Mux::from_bytes(&webp).unwrap().features().unwrap();
+        assert_eq!(
+            features,
+            ContainerFeatures::all() - ContainerFeatures::ANIMATION
+        );
+        assert_eq!(features.bits(), mux_flags(&webp));
+
+        let mut mux = Mux::new().unwrap();
+        mux.push_frame(&frame, &MuxFrameInfo::default()).unwrap();
+        mux.push_frame(&frame, &MuxFrameInfo::default()).unwrap();
+        let webp = mux.assemble().unwrap();
+        let features = Mux::from_bytes(&webp).unwrap().features().unwrap();
+        assert!(features.has_animation() && features.has_alpha());
+        unsafe {
+            let data = webp_data(&webp);
+            let demux = sys::WebPDemux(&data);
+            assert!(!demux.is_null());
+            let flags = sys::WebPDemuxGetI(demux, sys::WebPFormatFeature::WEBP_FF_FORMAT_FLAGS);
+            assert_eq!(ContainerFeatures::from_bits_truncate(flags), features);
+            sys::WebPDemuxDelete(demux);
+        }
+    }
+
+    #[test]
+    fn test_mux_borrowed() {
+        let red = solid_frame(4, 4, [255, 0, 0, 255]);
+        let green = solid_frame(4, 4, [0, 255, 0, 255]);
+
+        let webp = {
+            let mut mux = Mux::new().unwrap();
+            mux.push_frame_borrowed(&red, &MuxFrameInfo::default())
+                .unwrap();
+            mux.push_frame_borrowed(&green, &MuxFrameInfo::default())
+                .unwrap();
+            mux.assemble().unwrap()
+        };
+        let mux = Mux::from_bytes_borrowed(&webp).unwrap();
+        assert_eq!(mux.num_frames().unwrap(), 2);
+        let (bitstream, _) = mux.frame(1).unwrap();
+        assert_eq!(&image_payload(&bitstream), &image_payload(&green));
+
+        let mut still = Mux::new().unwrap();
+        still.set_image_borrowed(&red).unwrap();
+        still.set_xmp(b"<x:xmpmeta/>").unwrap();
+        let webp = still.assemble().unwrap();
+        assert_eq!(
+            WebPDecodeRGBA(&webp).unwrap(),
+            WebPDecodeRGBA(&red).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mux_owned_outlives_input() {
+        fn parse(data: Vec<u8>) -> Mux<'static> {
+            let mut mux = Mux::new().unwrap();
+            mux.set_image(&data).unwrap();
+            mux
+        }
+
+        let red = solid_frame(4, 4, [255, 0, 0, 255]);
+        let mut mux = parse(red.to_vec());
+        let mut copied = Mux::from_bytes(&mux.assemble().unwrap()).unwrap();
+        assert_eq!(
+            WebPDecodeRGBA(&copied.assemble().unwrap()).unwrap(),
+            WebPDecodeRGBA(&red).unwrap()
+        );
+    }
+}
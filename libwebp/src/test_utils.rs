@@ -1,6 +1,9 @@
 use approx::AbsDiffEq;
 use png;
 
+use boxed::WebpBox;
+use encode::WebPEncodeLosslessRGBA;
+
 pub(crate) fn test_cases() -> &'static [TestCase] {
     lazy_static! {
         static ref TEST_CASES: Vec<TestCase> = {
@@ -100,6 +103,18 @@ impl Image {
         &self.data
     }
 
+    pub(crate) fn sub_image(&self, left: u32, top: u32, width: u32, height: u32) -> Image {
+        assert!(left + width <= self.width && top + height <= self.height);
+        let pixel_len = self.color_type.byte_len();
+        let stride = width as usize * pixel_len;
+        let mut data = Vec::with_capacity(stride * height as usize);
+        for y in top as usize..(top + height) as usize {
+            let start = y * self.stride as usize + left as usize * pixel_len;
+            data.extend_from_slice(&self.data[start..start + stride]);
+        }
+        Image::new(self.color_type, width, height, stride as u32, data)
+    }
+
     pub(crate) fn to_opaque(&self) -> Image {
         let mut this = self.clone();
         this.make_opaque();
@@ -196,6 +211,8 @@ impl AbsDiffEq for Image {
     }
 }
 
+// named after libwebp's `MODE_*` colorspaces
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) enum ColorType {
     RGBA,
@@ -276,6 +293,17 @@ impl ColorType {
     }
 }
 
+// Encodes a single-colour image, for building animations.
+pub(crate) fn solid_frame(width: u32, height: u32, rgba: [u8; 4]) -> WebpBox<[u8]> {
+    let data = rgba
+        .iter()
+        .cloned()
+        .cycle()
+        .take((width * height * 4) as usize)
+        .collect::<Vec<_>>();
+    WebPEncodeLosslessRGBA(&data, width, height, width * 4).unwrap()
+}
+
 pub(crate) fn decode_png(data: &[u8]) -> Image {
     use png::{self, Decoder};
     use std::io::Cursor;